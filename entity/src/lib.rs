@@ -1,5 +1,11 @@
 pub mod prelude;
 
+pub mod alternative_translations;
+pub mod examples;
+pub mod export_items;
+pub mod exports;
+pub mod media;
 pub mod seaql_migrations;
 pub mod words;
+pub mod wordset_words;
 pub mod wordsets;
@@ -1,5 +1,11 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.8.0
 
+pub use super::alternative_translations::Entity as AlternativeTranslations;
+pub use super::examples::Entity as Examples;
+pub use super::export_items::Entity as ExportItems;
+pub use super::exports::Entity as Exports;
+pub use super::media::Entity as Media;
 pub use super::seaql_migrations::Entity as SeaqlMigrations;
 pub use super::words::Entity as Words;
+pub use super::wordset_words::Entity as WordsetWords;
 pub use super::wordsets::Entity as Wordsets;
@@ -13,17 +13,59 @@ pub struct Model {
     pub translation: String,
     pub definition: String,
     pub is_gold_3000: bool,
-    pub examples: String,
-    pub wordset_id: i32,
-    pub exported: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub revision: i32,
+    pub removed_at: Option<i64>,
+    pub transcription: Option<String>,
+    pub part_of_speech: Option<String>,
+    pub sound_url: Option<String>,
+    pub image_url: Option<String>,
+    pub translation_note: Option<String>,
 }
 
-#[derive(Copy, Clone, Debug, EnumIter)]
-pub enum Relation {}
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::alternative_translations::Entity")]
+    AlternativeTranslations,
+    #[sea_orm(has_many = "super::examples::Entity")]
+    Examples,
+    #[sea_orm(has_many = "super::export_items::Entity")]
+    ExportItems,
+    #[sea_orm(has_many = "super::wordset_words::Entity")]
+    WordsetWords,
+}
+
+impl Related<super::alternative_translations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AlternativeTranslations.def()
+    }
+}
+
+impl Related<super::examples::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Examples.def()
+    }
+}
 
-impl RelationTrait for Relation {
-    fn def(&self) -> RelationDef {
-        panic!("No RelationDef")
+impl Related<super::export_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ExportItems.def()
+    }
+}
+
+impl Related<super::wordset_words::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WordsetWords.def()
+    }
+}
+
+impl Related<super::wordsets::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::wordset_words::Relation::Wordsets.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::wordset_words::Relation::Words.def().rev())
     }
 }
 
@@ -8,14 +8,28 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: i32,
     pub name: String,
+    pub removed_at: Option<i64>,
+    pub synced_at: Option<i64>,
 }
 
-#[derive(Copy, Clone, Debug, EnumIter)]
-pub enum Relation {}
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::wordset_words::Entity")]
+    WordsetWords,
+}
 
-impl RelationTrait for Relation {
-    fn def(&self) -> RelationDef {
-        panic!("No RelationDef")
+impl Related<super::wordset_words::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WordsetWords.def()
+    }
+}
+
+impl Related<super::words::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::wordset_words::Relation::Words.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::wordset_words::Relation::Wordsets.def().rev())
     }
 }
 
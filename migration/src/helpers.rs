@@ -0,0 +1,70 @@
+use sea_orm::{DbBackend, Statement};
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+/// Drops a column on any backend.
+///
+/// sea-query refuses to drop columns on SQLite, which does support it since 3.35.
+pub async fn drop_column<T, C>(
+    manager: &SchemaManager<'_>,
+    table: T,
+    column: C,
+) -> Result<(), DbErr>
+where
+    T: Iden + 'static,
+    C: Iden + 'static,
+{
+    match manager.get_database_backend() {
+        DbBackend::Sqlite => {
+            let sql = format!(
+                r#"ALTER TABLE "{}" DROP COLUMN "{}""#,
+                table.to_string(),
+                column.to_string()
+            );
+            let stmt = Statement::from_string(DbBackend::Sqlite, sql);
+            manager.get_connection().execute(stmt).await.map(|_| ())
+        }
+        _ => {
+            manager
+                .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                .await
+        }
+    }
+}
+
+/// Drops an index on any backend.
+///
+/// MySQL needs the table, while sea-query writes it for SQLite too, which fails on it.
+pub async fn drop_index<T>(manager: &SchemaManager<'_>, name: &str, table: T) -> Result<(), DbErr>
+where
+    T: Iden + 'static,
+{
+    match manager.get_database_backend() {
+        DbBackend::Sqlite => exec_sql(manager, &format!(r#"DROP INDEX "{name}""#)).await,
+        _ => {
+            manager
+                .drop_index(Index::drop().name(name).table(table).to_owned())
+                .await
+        }
+    }
+}
+
+/// Adds a column, SQLite takes a single change per `ALTER TABLE`
+pub async fn add_column<T>(
+    manager: &SchemaManager<'_>,
+    table: T,
+    column: &mut ColumnDef,
+) -> Result<(), DbErr>
+where
+    T: Iden + 'static,
+{
+    manager
+        .alter_table(Table::alter().table(table).add_column(column).to_owned())
+        .await
+}
+
+/// Runs plain SQL every supported backend understands
+pub async fn exec_sql(manager: &SchemaManager<'_>, sql: &str) -> Result<(), DbErr> {
+    let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
+    manager.get_connection().execute(stmt).await.map(|_| ())
+}
@@ -1,8 +1,18 @@
 pub use sea_orm_migration::prelude::*;
 
+mod helpers;
 mod m20220101_000001_create_table_words;
 mod m20220619_130453_create_wordset;
 mod m20220619_193726_unexported_words;
+mod m20220620_101500_examples_table;
+mod m20220621_183000_wordset_words;
+mod m20220625_120000_word_revisions;
+mod m20220627_090000_removed_entries;
+mod m20220701_100000_meaning_details;
+mod m20220703_150000_media;
+mod m20220705_120000_export_history;
+mod m20220706_090000_export_targets;
+mod m20220708_100000_words_search;
 
 pub struct Migrator;
 
@@ -13,6 +23,15 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table_words::Migration),
             Box::new(m20220619_130453_create_wordset::Migration),
             Box::new(m20220619_193726_unexported_words::Migration),
+            Box::new(m20220620_101500_examples_table::Migration),
+            Box::new(m20220621_183000_wordset_words::Migration),
+            Box::new(m20220625_120000_word_revisions::Migration),
+            Box::new(m20220627_090000_removed_entries::Migration),
+            Box::new(m20220701_100000_meaning_details::Migration),
+            Box::new(m20220703_150000_media::Migration),
+            Box::new(m20220705_120000_export_history::Migration),
+            Box::new(m20220706_090000_export_targets::Migration),
+            Box::new(m20220708_100000_words_search::Migration),
         ]
     }
 }
@@ -1,6 +1,4 @@
-use sea_orm::Statement;
 use sea_orm_migration::prelude::*;
-use sea_orm_migration::sea_orm::ConnectionTrait;
 
 pub struct Migration;
 
@@ -10,30 +8,45 @@ impl MigrationName for Migration {
     }
 }
 
+#[derive(Iden)]
+enum Words {
+    Table,
+    Id,
+    WordId,
+    DifficultyLevel,
+    Text,
+    Translation,
+    Definition,
+    #[iden = "is_gold_3000"]
+    IsGold3000,
+    Examples,
+    WordsetId,
+}
+
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = r#"
-        CREATE TABLE words (
-            id int NOT NULL PRIMARY KEY,
-            word_id int not null,
-            difficulty_level int not null,
-            text text not null,
-            translation text not null,
-            definition text not null,
-            is_gold_3000 boolean not null,
-            examples text not null,
-            wordset_id int not null
-        )"#;
-        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        manager
+            .create_table(
+                Table::create()
+                    .table(Words::Table)
+                    .col(ColumnDef::new(Words::Id).integer().not_null().primary_key())
+                    .col(ColumnDef::new(Words::WordId).integer().not_null())
+                    .col(ColumnDef::new(Words::DifficultyLevel).integer().not_null())
+                    .col(ColumnDef::new(Words::Text).text().not_null())
+                    .col(ColumnDef::new(Words::Translation).text().not_null())
+                    .col(ColumnDef::new(Words::Definition).text().not_null())
+                    .col(ColumnDef::new(Words::IsGold3000).boolean().not_null())
+                    .col(ColumnDef::new(Words::Examples).text().not_null())
+                    .col(ColumnDef::new(Words::WordsetId).integer().not_null())
+                    .to_owned(),
+            )
+            .await
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let stmt = Statement::from_string(
-            manager.get_database_backend(),
-            "drop table words".to_string(),
-        );
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        manager
+            .drop_table(Table::drop().table(Words::Table).to_owned())
+            .await
     }
 }
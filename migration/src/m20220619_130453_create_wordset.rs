@@ -1,6 +1,4 @@
-use sea_orm::Statement;
 use sea_orm_migration::prelude::*;
-use sea_orm_migration::sea_orm::ConnectionTrait;
 
 pub struct Migration;
 
@@ -10,21 +8,35 @@ impl MigrationName for Migration {
     }
 }
 
+#[derive(Iden)]
+enum Wordsets {
+    Table,
+    Id,
+    Name,
+}
+
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = r#"
-        CREATE TABLE wordsets (
-            id int NOT NULL PRIMARY KEY,
-            name text not null
-        )"#;
-        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        manager
+            .create_table(
+                Table::create()
+                    .table(Wordsets::Table)
+                    .col(
+                        ColumnDef::new(Wordsets::Id)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Wordsets::Name).text().not_null())
+                    .to_owned(),
+            )
+            .await
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = "DROP TABLE wordsets";
-        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        manager
+            .drop_table(Table::drop().table(Wordsets::Table).to_owned())
+            .await
     }
 }
@@ -1,6 +1,5 @@
-use sea_orm::Statement;
+use crate::helpers::{add_column, drop_column};
 use sea_orm_migration::prelude::*;
-use sea_orm_migration::sea_orm::ConnectionTrait;
 
 pub struct Migration;
 
@@ -10,17 +9,24 @@ impl MigrationName for Migration {
     }
 }
 
+#[derive(Iden)]
+enum Words {
+    Table,
+    Exported,
+}
+
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = r#"ALTER TABLE words add column exported bool default false;"#;
-        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        add_column(
+            manager,
+            Words::Table,
+            ColumnDef::new(Words::Exported).boolean().default(false),
+        )
+        .await
     }
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = r#"ALTER TABLE words drop column exported;"#;
-        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        drop_column(manager, Words::Table, Words::Exported).await
     }
 }
@@ -0,0 +1,132 @@
+use crate::helpers::{add_column, drop_column, exec_sql};
+use sea_orm::DbBackend;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220620_101500_examples_table"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    Id,
+    Examples,
+}
+
+#[derive(Iden)]
+enum Examples {
+    Table,
+    MeaningId,
+    Position,
+    Text,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        let backend = manager.get_database_backend();
+        manager
+            .create_table(
+                Table::create()
+                    .table(Examples::Table)
+                    .col(ColumnDef::new(Examples::MeaningId).integer().not_null())
+                    .col(ColumnDef::new(Examples::Position).integer().not_null())
+                    .col(ColumnDef::new(Examples::Text).text().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(Examples::MeaningId)
+                            .col(Examples::Position),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        let rows = conn
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Words::Id, Words::Examples])
+                        .from(Words::Table),
+                ),
+            )
+            .await?;
+        for row in rows {
+            let meaning_id: i32 = row.try_get("", "id")?;
+            let joined: String = row.try_get("", "examples")?;
+            let examples = split_joined_examples(&joined);
+            if examples.is_empty() {
+                continue;
+            }
+            let mut insert = Query::insert()
+                .into_table(Examples::Table)
+                .columns([Examples::MeaningId, Examples::Position, Examples::Text])
+                .to_owned();
+            for (position, text) in examples.into_iter().enumerate() {
+                insert.values_panic([meaning_id.into(), (position as i32).into(), text.into()]);
+            }
+            conn.execute(backend.build(&insert)).await?;
+        }
+        drop_column(manager, Words::Table, Words::Examples).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // MySQL can't give TEXT columns a default, every row gets a value below anyway
+        add_column(
+            manager,
+            Words::Table,
+            ColumnDef::new(Words::Examples).text(),
+        )
+        .await?;
+        let joined = match manager.get_database_backend() {
+            DbBackend::Sqlite => {
+                r#"SELECT group_concat(text, ',') FROM (
+                    SELECT text FROM examples
+                    WHERE examples.meaning_id = words.id
+                    ORDER BY position
+                )"#
+            }
+            DbBackend::Postgres => {
+                r#"SELECT string_agg(text, ',' ORDER BY position) FROM examples
+                WHERE examples.meaning_id = words.id"#
+            }
+            DbBackend::MySql => {
+                r#"SELECT group_concat(text ORDER BY position SEPARATOR ',') FROM examples
+                WHERE examples.meaning_id = words.id"#
+            }
+        };
+        exec_sql(
+            manager,
+            &format!("UPDATE words SET examples = coalesce(({joined}), '')"),
+        )
+        .await?;
+        manager
+            .drop_table(Table::drop().table(Examples::Table).to_owned())
+            .await
+    }
+}
+
+/// Examples used to be joined with a bare comma, while commas inside sentences are
+/// almost always followed by a space, so only split where the next char isn't one.
+fn split_joined_examples(joined: &str) -> Vec<String> {
+    let mut examples = Vec::new();
+    let mut current = String::new();
+    let mut chars = joined.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ',' && !matches!(chars.peek(), None | Some(' ' | '\t' | '\n')) {
+            examples.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    examples.push(current);
+    examples
+        .into_iter()
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
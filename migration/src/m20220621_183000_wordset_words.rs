@@ -0,0 +1,83 @@
+use crate::helpers::{add_column, drop_column, exec_sql};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220621_183000_wordset_words"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    Id,
+    WordsetId,
+}
+
+#[derive(Iden)]
+enum WordsetWords {
+    Table,
+    WordsetId,
+    MeaningId,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WordsetWords::Table)
+                    .col(ColumnDef::new(WordsetWords::WordsetId).integer().not_null())
+                    .col(ColumnDef::new(WordsetWords::MeaningId).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(WordsetWords::WordsetId)
+                            .col(WordsetWords::MeaningId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(WordsetWords::Table)
+                    .columns([WordsetWords::WordsetId, WordsetWords::MeaningId])
+                    .select_from(
+                        Query::select()
+                            .columns([Words::WordsetId, Words::Id])
+                            .from(Words::Table)
+                            .to_owned(),
+                    )
+                    .map_err(|e| DbErr::Migration(e.to_string()))?
+                    .to_owned(),
+            )
+            .await?;
+        drop_column(manager, Words::Table, Words::WordsetId).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_column(
+            manager,
+            Words::Table,
+            ColumnDef::new(Words::WordsetId)
+                .integer()
+                .not_null()
+                .default(0),
+        )
+        .await?;
+        exec_sql(
+            manager,
+            r#"
+            UPDATE words SET wordset_id = coalesce((
+                SELECT min(wordset_id) FROM wordset_words WHERE meaning_id = words.id
+            ), 0)"#,
+        )
+        .await?;
+        manager
+            .drop_table(Table::drop().table(WordsetWords::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use crate::helpers::{add_column, drop_column};
+use sea_orm_migration::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220625_120000_word_revisions"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    CreatedAt,
+    UpdatedAt,
+    Revision,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let columns = [
+            ColumnDef::new(Words::CreatedAt)
+                .big_integer()
+                .not_null()
+                .default(0)
+                .to_owned(),
+            ColumnDef::new(Words::UpdatedAt)
+                .big_integer()
+                .not_null()
+                .default(0)
+                .to_owned(),
+            ColumnDef::new(Words::Revision)
+                .integer()
+                .not_null()
+                .default(1)
+                .to_owned(),
+        ];
+        for mut column in columns {
+            add_column(manager, Words::Table, &mut column).await?;
+        }
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Words::Table)
+                    .values([
+                        (Words::CreatedAt, now.into()),
+                        (Words::UpdatedAt, now.into()),
+                    ])
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_column(manager, Words::Table, Words::CreatedAt).await?;
+        drop_column(manager, Words::Table, Words::UpdatedAt).await?;
+        drop_column(manager, Words::Table, Words::Revision).await
+    }
+}
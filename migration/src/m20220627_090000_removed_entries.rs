@@ -0,0 +1,66 @@
+use crate::helpers::{add_column, drop_column};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220627_090000_removed_entries"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    RemovedAt,
+}
+
+#[derive(Iden)]
+enum WordsetWords {
+    Table,
+    RemovedAt,
+}
+
+#[derive(Iden)]
+enum Wordsets {
+    Table,
+    RemovedAt,
+    SyncedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_column(
+            manager,
+            Words::Table,
+            ColumnDef::new(Words::RemovedAt).big_integer(),
+        )
+        .await?;
+        add_column(
+            manager,
+            WordsetWords::Table,
+            ColumnDef::new(WordsetWords::RemovedAt).big_integer(),
+        )
+        .await?;
+        add_column(
+            manager,
+            Wordsets::Table,
+            ColumnDef::new(Wordsets::RemovedAt).big_integer(),
+        )
+        .await?;
+        add_column(
+            manager,
+            Wordsets::Table,
+            ColumnDef::new(Wordsets::SyncedAt).big_integer(),
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_column(manager, Words::Table, Words::RemovedAt).await?;
+        drop_column(manager, WordsetWords::Table, WordsetWords::RemovedAt).await?;
+        drop_column(manager, Wordsets::Table, Wordsets::RemovedAt).await?;
+        drop_column(manager, Wordsets::Table, Wordsets::SyncedAt).await
+    }
+}
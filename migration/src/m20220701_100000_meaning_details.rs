@@ -0,0 +1,92 @@
+use crate::helpers::{add_column, drop_column};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220701_100000_meaning_details"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    Transcription,
+    PartOfSpeech,
+    SoundUrl,
+    ImageUrl,
+    TranslationNote,
+}
+
+#[derive(Iden)]
+enum AlternativeTranslations {
+    Table,
+    MeaningId,
+    Position,
+    Text,
+    Translation,
+}
+
+const NEW_COLUMNS: [Words; 5] = [
+    Words::Transcription,
+    Words::PartOfSpeech,
+    Words::SoundUrl,
+    Words::ImageUrl,
+    Words::TranslationNote,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in NEW_COLUMNS {
+            add_column(manager, Words::Table, ColumnDef::new(column).text()).await?;
+        }
+        manager
+            .create_table(
+                Table::create()
+                    .table(AlternativeTranslations::Table)
+                    .col(
+                        ColumnDef::new(AlternativeTranslations::MeaningId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AlternativeTranslations::Position)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AlternativeTranslations::Text)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AlternativeTranslations::Translation)
+                            .text()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(AlternativeTranslations::MeaningId)
+                            .col(AlternativeTranslations::Position),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(AlternativeTranslations::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in NEW_COLUMNS {
+            drop_column(manager, Words::Table, column).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220703_150000_media"
+    }
+}
+
+#[derive(Iden)]
+enum Media {
+    Table,
+    Url,
+    Path,
+    Size,
+    Checksum,
+    ContentType,
+    DownloadedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Media::Table)
+                    // MySQL can't key TEXT columns
+                    .col(ColumnDef::new(Media::Url).string().not_null().primary_key())
+                    .col(ColumnDef::new(Media::Path).text().not_null())
+                    .col(ColumnDef::new(Media::Size).big_integer().not_null())
+                    .col(ColumnDef::new(Media::Checksum).text().not_null())
+                    .col(ColumnDef::new(Media::ContentType).text())
+                    .col(ColumnDef::new(Media::DownloadedAt).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Media::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,141 @@
+use crate::helpers::{add_column, drop_column, exec_sql};
+use sea_orm_migration::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220705_120000_export_history"
+    }
+}
+
+#[derive(Iden)]
+enum Words {
+    Table,
+    Exported,
+}
+
+#[derive(Iden)]
+enum Exports {
+    Table,
+    Id,
+    CreatedAt,
+    Format,
+    Destination,
+    WordCount,
+    Options,
+}
+
+#[derive(Iden)]
+enum ExportItems {
+    Table,
+    ExportId,
+    MeaningId,
+    Revision,
+    Position,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        manager
+            .create_table(
+                Table::create()
+                    .table(Exports::Table)
+                    .col(
+                        ColumnDef::new(Exports::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Exports::CreatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Exports::Format).text().not_null())
+                    .col(ColumnDef::new(Exports::Destination).text().not_null())
+                    .col(ColumnDef::new(Exports::WordCount).integer().not_null())
+                    // options the batch was written with as JSON, batches migrated from the
+                    // exported flag have none
+                    .col(ColumnDef::new(Exports::Options).text())
+                    .to_owned(),
+            )
+            .await?;
+        // items outlive pruned words, so meaning_id isn't a foreign key
+        manager
+            .create_table(
+                Table::create()
+                    .table(ExportItems::Table)
+                    .col(ColumnDef::new(ExportItems::ExportId).integer().not_null())
+                    .col(ColumnDef::new(ExportItems::MeaningId).integer().not_null())
+                    .col(ColumnDef::new(ExportItems::Revision).integer().not_null())
+                    .col(ColumnDef::new(ExportItems::Position).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(ExportItems::ExportId)
+                            .col(ExportItems::MeaningId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("export_items_export")
+                            .from(ExportItems::Table, ExportItems::ExportId)
+                            .to(Exports::Table, Exports::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("export_items_meaning")
+                    .table(ExportItems::Table)
+                    .col(ExportItems::MeaningId)
+                    .col(ExportItems::Revision)
+                    .to_owned(),
+            )
+            .await?;
+        // words exported before the history existed become a batch of unknown format
+        exec_sql(
+            manager,
+            &format!(
+                "INSERT INTO exports (created_at, format, destination, word_count)
+                SELECT {now}, 'unknown', '', count(*) FROM words WHERE exported"
+            ),
+        )
+        .await?;
+        exec_sql(manager, "DELETE FROM exports WHERE word_count = 0").await?;
+        exec_sql(
+            manager,
+            r#"INSERT INTO export_items (export_id, meaning_id, revision, position)
+            SELECT e.id, w.id, w.revision, ROW_NUMBER() OVER (ORDER BY w.id) - 1
+            FROM words w, exports e WHERE w.exported"#,
+        )
+        .await?;
+        drop_column(manager, Words::Table, Words::Exported).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        add_column(
+            manager,
+            Words::Table,
+            ColumnDef::new(Words::Exported).boolean().default(false),
+        )
+        .await?;
+        exec_sql(
+            manager,
+            r#"UPDATE words SET exported = true WHERE EXISTS (
+                SELECT 1 FROM export_items i
+                WHERE i.meaning_id = words.id AND i.revision = words.revision
+            )"#,
+        )
+        .await?;
+        manager
+            .drop_table(Table::drop().table(ExportItems::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Exports::Table).to_owned())
+            .await
+    }
+}
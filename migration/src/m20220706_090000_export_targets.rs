@@ -0,0 +1,60 @@
+use crate::helpers::{add_column, drop_column, drop_index, exec_sql};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220706_090000_export_targets"
+    }
+}
+
+#[derive(Iden)]
+enum Exports {
+    Table,
+    Target,
+    ResetAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // a string rather than text, MySQL can't index TEXT columns
+        add_column(
+            manager,
+            Exports::Table,
+            ColumnDef::new(Exports::Target)
+                .string()
+                .not_null()
+                .default(""),
+        )
+        .await?;
+        add_column(
+            manager,
+            Exports::Table,
+            ColumnDef::new(Exports::ResetAt).big_integer(),
+        )
+        .await?;
+        // xlsx was the only format back when a single flag was kept
+        exec_sql(
+            manager,
+            "UPDATE exports SET target = CASE format WHEN 'unknown' THEN 'xlsx' ELSE format END",
+        )
+        .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("exports_target")
+                    .table(Exports::Table)
+                    .col(Exports::Target)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_index(manager, "exports_target", Exports::Table).await?;
+        drop_column(manager, Exports::Table, Exports::ResetAt).await?;
+        drop_column(manager, Exports::Table, Exports::Target).await
+    }
+}
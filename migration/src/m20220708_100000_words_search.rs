@@ -0,0 +1,113 @@
+use crate::helpers::exec_sql;
+use sea_orm::DbBackend;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220708_100000_words_search"
+    }
+}
+
+/// Examples of the word whose id is in `id` joined in their original order, one per line.
+///
+/// `group_concat` as an aggregate takes rows in no particular order, even from an ordered
+/// subquery. As a window function it goes through them in the order of the window.
+fn examples_of(id: &str) -> String {
+    format!(
+        "(SELECT group_concat(text, char(10)) OVER (ORDER BY position \
+            ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) \
+        FROM examples WHERE meaning_id = {id} LIMIT 1)"
+    )
+}
+
+/// Full-text index of words with triggers keeping it up to date.
+///
+/// unicode61 folds case of both Cyrillic and Latin letters, porter stems English words
+/// and passes Russian ones through as they are.
+fn up_statements() -> Vec<String> {
+    vec![
+        "CREATE VIRTUAL TABLE words_search USING fts5(\
+            text, translation, definition, examples, \
+            tokenize = 'porter unicode61 remove_diacritics 2', prefix = '2 3')"
+            .to_string(),
+        format!(
+            "INSERT INTO words_search (rowid, text, translation, definition, examples) \
+            SELECT id, text, translation, definition, {} FROM words",
+            examples_of("id")
+        ),
+        format!(
+            "CREATE TRIGGER words_search_insert AFTER INSERT ON words BEGIN \
+                INSERT INTO words_search (rowid, text, translation, definition, examples) \
+                VALUES (new.id, new.text, new.translation, new.definition, {}); \
+            END",
+            examples_of("new.id")
+        ),
+        "CREATE TRIGGER words_search_update \
+        AFTER UPDATE OF text, translation, definition ON words BEGIN \
+            UPDATE words_search \
+            SET text = new.text, translation = new.translation, definition = new.definition \
+            WHERE rowid = new.id; \
+        END"
+        .to_string(),
+        "CREATE TRIGGER words_search_delete AFTER DELETE ON words BEGIN \
+            DELETE FROM words_search WHERE rowid = old.id; \
+        END"
+        .to_string(),
+        format!(
+            "CREATE TRIGGER examples_search_insert AFTER INSERT ON examples BEGIN \
+                UPDATE words_search SET examples = {} WHERE rowid = new.meaning_id; \
+            END",
+            examples_of("new.meaning_id")
+        ),
+        format!(
+            "CREATE TRIGGER examples_search_update AFTER UPDATE ON examples BEGIN \
+                UPDATE words_search SET examples = {} WHERE rowid = old.meaning_id; \
+                UPDATE words_search SET examples = {} WHERE rowid = new.meaning_id; \
+            END",
+            examples_of("old.meaning_id"),
+            examples_of("new.meaning_id")
+        ),
+        format!(
+            "CREATE TRIGGER examples_search_delete AFTER DELETE ON examples BEGIN \
+                UPDATE words_search SET examples = {} WHERE rowid = old.meaning_id; \
+            END",
+            examples_of("old.meaning_id")
+        ),
+    ]
+}
+
+const DOWN: &[&str] = &[
+    "DROP TRIGGER examples_search_delete",
+    "DROP TRIGGER examples_search_update",
+    "DROP TRIGGER examples_search_insert",
+    "DROP TRIGGER words_search_delete",
+    "DROP TRIGGER words_search_update",
+    "DROP TRIGGER words_search_insert",
+    "DROP TABLE words_search",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // only SQLite gets an index, other databases are searched without one
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        for sql in up_statements() {
+            exec_sql(manager, &sql).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        for sql in DOWN {
+            exec_sql(manager, sql).await?;
+        }
+        Ok(())
+    }
+}
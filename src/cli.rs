@@ -1,4 +1,16 @@
-use clap::{ArgEnum, Args, Parser, Subcommand};
+use crate::view::Output;
+use chrono::NaiveDate;
+use clap::builder::PossibleValuesParser;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use regex::Regex;
+use skyeng_words::client::{ClientConfig, RateLimit, RetryPolicy, Session};
+use skyeng_words::db::WordFilter;
+use skyeng_words::export::{Column, ExportOptions, Quoting, Registry};
+use skyeng_words::media::MediaStore;
+use skyeng_words::sync;
+use std::path::PathBuf;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
 
 #[derive(Parser)]
 pub struct Cli {
@@ -8,29 +20,353 @@ pub struct Cli {
     pub login: Option<String>,
     #[clap(env = "SKYENG_PASSWORD")]
     pub password: Option<String>,
+    #[clap(flatten)]
+    pub api: ApiUrls,
+    #[clap(flatten)]
+    pub http: Http,
+    /// Where the session is cached between runs, defaults to the user's cache directory
+    #[clap(long, env = "SKYENG_SESSION_FILE", value_parser)]
+    pub session_file: Option<PathBuf>,
+    /// Where downloaded media are kept, defaults to `media` next to an SQLite database
+    #[clap(long, env = "SKYENG_MEDIA_DIR", value_parser)]
+    pub media_dir: Option<PathBuf>,
     #[clap(subcommand)]
     pub command: Command,
 }
 
+/// Base URLs of the Skyeng services, override them to run against a mock server
+#[derive(Debug, Args)]
+pub struct ApiUrls {
+    #[clap(long, env = "SKYENG_ID_URL", default_value = "https://id.skyeng.ru")]
+    pub id_url: String,
+    #[clap(
+        long,
+        env = "SKYENG_STUDENT_API_URL",
+        default_value = "https://api-student.skyeng.ru"
+    )]
+    pub student_api_url: String,
+    #[clap(
+        long,
+        env = "SKYENG_WORDS_API_URL",
+        default_value = "https://api-words.skyeng.ru"
+    )]
+    pub words_api_url: String,
+    #[clap(
+        long,
+        env = "SKYENG_DICTIONARY_URL",
+        default_value = "https://dictionary.skyeng.ru"
+    )]
+    pub dictionary_url: String,
+}
+
+/// Retries, rate limiting and parallelism of requests to Skyeng
+#[derive(Debug, Args)]
+pub struct Http {
+    /// Attempts per request before giving up, 1 disables retries
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), default_value = "5")]
+    pub max_attempts: u32,
+    /// Average number of requests per second, 0 disables rate limiting
+    #[clap(long, value_parser, default_value = "5")]
+    pub requests_per_second: f64,
+    /// Number of requests allowed to go at once before rate limiting kicks in
+    #[clap(long, value_parser, default_value = "10")]
+    pub burst: u32,
+    /// Requests in flight at once while syncing, across all wordsets
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..), default_value = "4")]
+    pub concurrency: u16,
+}
+
+impl Cli {
+    pub fn client_config(&self) -> ClientConfig {
+        ClientConfig {
+            id_url: self.api.id_url.clone(),
+            student_api_url: self.api.student_api_url.clone(),
+            words_api_url: self.api.words_api_url.clone(),
+            dictionary_url: self.api.dictionary_url.clone(),
+            retry: RetryPolicy {
+                max_attempts: self.http.max_attempts,
+                ..RetryPolicy::default()
+            },
+            rate_limit: (self.http.requests_per_second > 0.0).then_some(RateLimit {
+                per_second: self.http.requests_per_second,
+                burst: self.http.burst,
+            }),
+            session_file: self.session_file.clone().or_else(Session::default_path),
+        }
+    }
+
+    pub fn media_dir(&self) -> Option<PathBuf> {
+        self.media_dir
+            .clone()
+            .or_else(|| MediaStore::default_dir(&self.db_url))
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Command {
-    Sync,
-    SyncWordset(IdOrName),
-    Export(Export),
+    Sync {
+        /// Also download pronunciation audio and images
+        #[clap(long, action)]
+        with_media: bool,
+    },
+    SyncWordset {
+        #[clap(flatten)]
+        wordset: IdOrName,
+        /// Also download pronunciation audio and images
+        #[clap(long, action)]
+        with_media: bool,
+    },
+    Export(Box<Export>),
+    /// Search saved words by their text, translation, definition and examples
+    ///
+    /// English words match their other forms, "tickets" finds "ticket". Russian words are
+    /// not stemmed and only match as typed, search for the beginning of a word to find
+    /// its forms: "билетов" doesn't find "билеты", "билет" does.
+    Search {
+        /// Words to look for, each of them matches the beginning of a word
+        #[clap(required = true, value_parser)]
+        query: Vec<String>,
+        /// Show at most N words
+        #[clap(long, value_name = "N", value_parser, default_value = "20")]
+        limit: usize,
+        /// Also search words removed from Skyeng
+        #[clap(long, action)]
+        include_removed: bool,
+    },
+    /// Inspect stored wordsets
+    Wordsets {
+        #[clap(subcommand)]
+        command: WordsetsCommand,
+    },
+    /// Inspect stored words
+    Words {
+        #[clap(subcommand)]
+        command: WordsCommand,
+    },
+    /// Vocabulary size and growth, export progress per target
+    Stats {
+        /// Export progress to this target only, defaults to every target exported to
+        #[clap(long, value_parser)]
+        target: Option<String>,
+        /// table, json or plain
+        #[clap(long, value_parser, default_value = "table")]
+        output: Output,
+    },
+    /// List past exports, the latest first
+    History {
+        /// Only exports to the target
+        #[clap(long, value_parser)]
+        target: Option<String>,
+    },
+    /// Forget what was exported to the target, its next export includes every word again
+    Reset {
+        #[clap(long, value_parser)]
+        target: String,
+    },
+    /// Delete words and wordsets removed upstream for good
+    Prune,
+    /// Forget the cached session, the next run logs in again
+    Logout,
+}
+
+#[derive(Subcommand)]
+pub enum WordsetsCommand {
+    /// List wordsets with the number of their words
+    List {
+        /// Count words not exported to this target yet, defaults to xlsx like export does
+        #[clap(long, value_parser)]
+        target: Option<String>,
+        /// Also list wordsets removed from Skyeng
+        #[clap(long, action)]
+        include_removed: bool,
+        /// table, json or plain
+        #[clap(long, value_parser, default_value = "table")]
+        output: Output,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WordsCommand {
+    /// List words, filters are the ones export takes
+    List {
+        /// Only words not exported to the target yet
+        #[clap(long, value_name = "TARGET", value_parser)]
+        not_exported_to: Option<String>,
+        /// Also list words removed from Skyeng
+        #[clap(long, action)]
+        include_removed: bool,
+        #[clap(flatten)]
+        filter: Filter,
+        /// table, json or plain
+        #[clap(long, value_parser, default_value = "table")]
+        output: Output,
+    },
+    /// Show everything stored about a word
+    Show {
+        /// Meaning id, as listed by `words list`
+        #[clap(value_parser)]
+        id: i32,
+        /// table, json or plain
+        #[clap(long, value_parser, default_value = "table")]
+        output: Output,
+    },
 }
 
 #[derive(Debug, Args)]
 pub struct Export {
+    /// Path of the exported file, `-` writes to stdout
     pub destination: String,
-    #[clap(arg_enum, value_parser, default_value = "xlsx")]
-    pub format: Format,
+    /// Output format, xlsx by default
+    #[clap(value_parser)]
+    pub format: Option<String>,
+    /// Export everything, not just words new to the target
     #[clap(short, long, action)]
     pub all: bool,
+    /// Name of the place words go to, each target keeps its own export state.
+    /// Defaults to the format's name.
+    #[clap(long, value_parser)]
+    pub target: Option<String>,
+    /// Write a past batch again, in its format and with its options, see `history`
+    #[clap(
+        long,
+        value_name = "ID",
+        value_parser,
+        conflicts_with_all = &[
+            "format", "all", "target", "include-removed", "wordsets", "min-difficulty",
+            "max-difficulty", "gold-only", "since", "until", "pattern", "columns", "delimiter",
+            "quoting", "no-header", "bom", "examples-limit", "examples-separator", "with-media",
+        ]
+    )]
+    pub batch: Option<i32>,
+    /// Also export words removed from Skyeng
+    #[clap(long, action)]
+    pub include_removed: bool,
+    /// Overwrite the destination if it already exists
+    #[clap(short, long, action)]
+    pub force: bool,
+    /// Comma separated columns to write, defaults to the format's own layout
+    #[clap(long, value_parser, use_value_delimiter = true)]
+    pub columns: Option<Vec<Column>>,
+    /// Field delimiter for delimited formats, a single character or `tab`
+    #[clap(long, value_parser = parse_delimiter)]
+    pub delimiter: Option<u8>,
+    /// When to quote fields of delimited formats: necessary, always or never
+    #[clap(long, value_parser, default_value = "necessary")]
+    pub quoting: Quoting,
+    /// Leave out the header row
+    #[clap(long, action)]
+    pub no_header: bool,
+    /// Start text output with a UTF-8 byte order mark, helps Excel detect the encoding
+    #[clap(long, action)]
+    pub bom: bool,
+    /// Export at most N examples per word
+    #[clap(long, value_parser)]
+    pub examples_limit: Option<usize>,
+    /// Separator between examples sharing a field
+    #[clap(long, value_parser)]
+    pub examples_separator: Option<String>,
+    /// Embed or link media downloaded by `sync --with-media`
+    #[clap(long, action)]
+    pub with_media: bool,
+    /// Show what would be exported instead of writing it
+    #[clap(long, action)]
+    pub dry_run: bool,
+    /// Rows of words shown by --dry-run
+    #[clap(long, value_name = "N", value_parser, default_value = "20")]
+    pub preview_rows: usize,
+    /// Write the file without recording it in the history, the words stay new to the target
+    #[clap(long, action)]
+    pub no_mark: bool,
+    #[clap(flatten)]
+    pub filter: Filter,
+}
+
+/// Narrows down the words to export, filters combine
+#[derive(Debug, Args)]
+pub struct Filter {
+    /// Only words of the wordset, an id or a name, may be repeated
+    #[clap(long = "wordset", value_name = "ID|NAME", value_parser)]
+    pub wordsets: Vec<sync::IdOrName>,
+    #[clap(long, value_parser)]
+    pub min_difficulty: Option<i32>,
+    #[clap(long, value_parser)]
+    pub max_difficulty: Option<i32>,
+    /// Only words from the Gold 3000 list
+    #[clap(long, action)]
+    pub gold_only: bool,
+    /// Only words added on this day (YYYY-MM-DD, UTC) or later
+    #[clap(long, value_parser = parse_date)]
+    pub since: Option<NaiveDate>,
+    /// Only words added on this day (YYYY-MM-DD, UTC) or earlier
+    #[clap(long, value_parser = parse_date)]
+    pub until: Option<NaiveDate>,
+    /// Only words whose text or translation matches the regular expression
+    #[clap(long = "match", value_name = "REGEX", value_parser = parse_regex)]
+    pub pattern: Option<Regex>,
+}
+
+impl Filter {
+    /// Wordsets are left for the caller to resolve, names need the database
+    pub fn word_filter(&self) -> WordFilter {
+        WordFilter {
+            min_difficulty: self.min_difficulty,
+            max_difficulty: self.max_difficulty,
+            gold_only: self.gold_only,
+            since: self.since.map(start_of_day),
+            until: self.until.map(|day| start_of_day(day) + SECS_PER_DAY),
+            pattern: self.pattern.clone(),
+            ..WordFilter::default()
+        }
+    }
+}
+
+impl Export {
+    /// Wordsets are left for the caller to resolve, names need the database
+    pub fn filter(&self, target: &str) -> WordFilter {
+        WordFilter {
+            not_exported_to: (!self.all).then(|| target.to_string()),
+            include_removed: self.include_removed,
+            ..self.filter.word_filter()
+        }
+    }
+
+    pub fn options(&self) -> ExportOptions {
+        ExportOptions {
+            columns: self.columns.clone(),
+            delimiter: self.delimiter,
+            quoting: self.quoting,
+            header: !self.no_header,
+            bom: self.bom,
+            examples_limit: self.examples_limit,
+            examples_separator: self.examples_separator.clone(),
+            media_dir: None,
+        }
+    }
+}
+
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ => match s.as_bytes() {
+            [b] if b.is_ascii() => Ok(*b),
+            _ => Err("delimiter must be a single ascii character".to_string()),
+        },
+    }
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| format!("expected YYYY-MM-DD: {e}"))
+}
+
+fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
 }
 
-#[derive(ArgEnum, Clone, Copy, Debug)]
-pub enum Format {
-    Xlsx,
+/// Unix time of the day's midnight in UTC
+fn start_of_day(day: NaiveDate) -> i64 {
+    day.and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .timestamp()
 }
 
 #[derive(Debug, Args)]
@@ -41,6 +377,14 @@ pub struct IdOrName {
     pub name: Option<String>,
 }
 
-pub fn parse() -> Cli {
-    Cli::parse()
+/// Parses arguments, accepting every format known to `registry`
+pub fn parse(registry: &Registry) -> Cli {
+    let mut cmd = Cli::command();
+    if let Some(export) = cmd.find_subcommand_mut("export") {
+        let formats: Vec<&'static str> = registry.names().collect();
+        *export = std::mem::take(export).mut_arg("format", |arg| {
+            arg.value_parser(PossibleValuesParser::new(formats))
+        });
+    }
+    Cli::from_arg_matches(&cmd.get_matches()).unwrap_or_else(|e| e.exit())
 }
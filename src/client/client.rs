@@ -1,20 +1,26 @@
-use anyhow::{bail, Result};
-use once_cell::sync::OnceCell;
+use reqwest::header::CONTENT_TYPE;
 use reqwest::IntoUrl;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tokio::sync::Mutex;
 
+use super::error::{Error, Result};
 use super::models::*;
-use crate::error::*;
+use super::rate_limit::{RateLimit, RateLimiter};
+use super::retry::{self, RetryPolicy};
+use super::session::Session;
 use reqwest::{
     Client as ReqClient, ClientBuilder as ReqClientBuilder, RequestBuilder as ReqRequestBuilder,
+    Response, StatusCode,
 };
 use scraper::{Html, Selector};
+use serde::de::DeserializeOwned;
 
 struct Credentials {
-    token: OnceCell<String>,
     login: String,
     password: String,
-    user_id: OnceCell<i32>,
+    session: RwLock<Option<Session>>,
 }
 
 impl Credentials {
@@ -22,120 +28,290 @@ impl Credentials {
         Self {
             login,
             password,
-            token: OnceCell::new(),
-            user_id: OnceCell::new(),
+            session: RwLock::new(None),
         }
     }
-    pub fn token(&self) -> &str {
-        self.token.get().expect("token not set yet").as_str()
+    pub fn token(&self) -> Result<String> {
+        Ok(self.session()?.token)
     }
-    pub fn user_id(&self) -> &i32 {
-        self.user_id.get().expect("user_id not set yet")
+    pub fn user_id(&self) -> Result<i32> {
+        Ok(self.session()?.user_id)
     }
-    pub fn set_token(&self, token: String) {
-        self.token.set(token).expect("token already set");
+    fn session(&self) -> Result<Session> {
+        self.session
+            .read()
+            .expect("session lock poisoned")
+            .clone()
+            .ok_or(Error::NotLoggedIn)
     }
-    pub fn set_user_id(&self, user_id: i32) {
-        self.user_id.set(user_id).expect("user_id already set");
+    pub fn set_session(&self, session: Session) {
+        *self.session.write().expect("session lock poisoned") = Some(session);
+    }
+}
+
+/// Base URLs of the Skyeng services the client talks to and how it treats them
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub id_url: String,
+    pub student_api_url: String,
+    pub words_api_url: String,
+    pub dictionary_url: String,
+    pub retry: RetryPolicy,
+    /// `None` disables rate limiting
+    pub rate_limit: Option<RateLimit>,
+    /// Where the session is cached between runs, `None` logs in every time
+    pub session_file: Option<PathBuf>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            id_url: "https://id.skyeng.ru".to_string(),
+            student_api_url: "https://api-student.skyeng.ru".to_string(),
+            words_api_url: "https://api-words.skyeng.ru".to_string(),
+            dictionary_url: "https://dictionary.skyeng.ru".to_string(),
+            retry: RetryPolicy::default(),
+            rate_limit: Some(RateLimit::default()),
+            session_file: None,
+        }
     }
 }
 
 pub struct Client {
     inner: ReqClient,
     creds: Credentials,
+    limiter: RateLimiter,
+    /// Held while logging in so concurrent requests failing with 401 log in only once
+    login_lock: Mutex<()>,
+    config: ClientConfig,
 }
 
 impl Client {
     pub fn new(login: String, password: String) -> Result<Client> {
+        Self::with_config(login, password, ClientConfig::default())
+    }
+
+    pub fn with_config(login: String, password: String, config: ClientConfig) -> Result<Client> {
         Ok(Client {
             inner: ReqClientBuilder::new().cookie_store(true).build()?,
             creds: Credentials::new(login, password),
+            limiter: RateLimiter::new(config.rate_limit.clone()),
+            login_lock: Mutex::new(()),
+            config,
         })
     }
-    fn get<U: IntoUrl>(&self, url: U) -> ReqRequestBuilder {
-        self.inner
-            .get(url)
-            .header("Authorization", format!("Bearer {}", self.creds.token()))
+
+    fn url(base: &str, path: &str) -> String {
+        format!("{}{path}", base.trim_end_matches('/'))
+    }
+    /// Sends a request that is safe to repeat, retrying rate limited, failed upstream
+    /// and interrupted attempts according to the retry policy
+    async fn send(&self, request: ReqRequestBuilder) -> Result<Response> {
+        let policy = &self.config.retry;
+        let mut attempt = 1;
+        loop {
+            let current = Self::try_clone(&request)?;
+            self.limiter.acquire().await;
+            let delay = match current.send().await {
+                Ok(resp)
+                    if attempt < policy.max_attempts
+                        && retry::is_retryable_status(resp.status()) =>
+                {
+                    log::warn!("{} answered {}, retrying", resp.url(), resp.status());
+                    policy.delay(attempt, Some(&resp))
+                }
+                Ok(resp) => return Self::check_status(resp).await,
+                Err(e) if attempt < policy.max_attempts && retry::is_retryable_error(&e) => {
+                    log::warn!("request failed: {e}, retrying");
+                    policy.delay(attempt, None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            log::debug!("attempt {attempt} failed, next one in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request that must not be repeated, e.g. submitting credentials
+    async fn send_once(&self, request: ReqRequestBuilder) -> Result<Response> {
+        self.limiter.acquire().await;
+        Ok(request.send().await?)
     }
 
-    fn post<U: IntoUrl>(&self, url: U) -> ReqRequestBuilder {
-        self.inner
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.creds.token()))
+    /// Sends a request on behalf of the logged in user. A 401 means the session has
+    /// expired, the client logs in again and repeats the request once.
+    async fn send_authorized(&self, request: ReqRequestBuilder) -> Result<Response> {
+        let token = self.creds.token()?;
+        let first = Self::try_clone(&request)?.bearer_auth(&token);
+        match self.send(first).await {
+            Err(e) if is_unauthorized(&e) => {
+                log::info!("session expired, logging in again");
+                self.relogin(&token).await?;
+                match self.send(request.bearer_auth(self.creds.token()?)).await {
+                    Err(e) if is_unauthorized(&e) => Err(Error::SessionExpired),
+                    resp => resp,
+                }
+            }
+            resp => resp,
+        }
+    }
+
+    /// Requests are built once and cloned for every attempt
+    fn try_clone(request: &ReqRequestBuilder) -> Result<ReqRequestBuilder> {
+        request.try_clone().ok_or(Error::NotRepeatable)
+    }
+
+    /// Turns unsuccessful responses into errors
+    async fn check_status(resp: Response) -> Result<Response> {
+        let status = resp.status();
+        match status {
+            _ if status.is_success() || status.is_redirection() => Ok(resp),
+            StatusCode::TOO_MANY_REQUESTS => Err(Error::RateLimited {
+                retry_after: retry::retry_after(&resp),
+            }),
+            _ => Err(Error::Status {
+                url: resp.url().to_string(),
+                status,
+                body: resp.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Reads the body as `T`, keeping the payload when it doesn't match
+    async fn json<T: DeserializeOwned>(resp: Response) -> Result<T> {
+        let url = resp.url().to_string();
+        let payload = resp.text().await?;
+        serde_json::from_str(&payload).map_err(|source| Error::Schema {
+            url,
+            payload,
+            source,
+        })
+    }
+
+    async fn relogin(&self, expired_token: &str) -> Result<()> {
+        let _guard = self.login_lock.lock().await;
+        if self.creds.token()? != expired_token {
+            // someone else has logged in while we were waiting
+            return Ok(());
+        }
+        self.login_with_credentials().await
+    }
+
+    fn get<U: IntoUrl>(&self, url: U) -> ReqRequestBuilder {
+        self.inner.get(url)
     }
 
     fn put<U: IntoUrl>(&self, url: U) -> ReqRequestBuilder {
-        self.inner
-            .put(url)
-            .header("Authorization", format!("Bearer {}", self.creds.token()))
+        self.inner.put(url)
     }
 
-    async fn get_user_id(&self) -> Result<i32> {
-        let resp: Users = self
-            .post("https://api-student.skyeng.ru/api/v2/users")
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(resp.users.first().unwrap().id)
+    async fn get_user_id(&self, token: &str) -> Result<i32> {
+        let url = Self::url(&self.config.student_api_url, "/api/v2/users");
+        // the token has just been issued, a 401 means it's rejected rather than expired
+        let resp = self.send(self.inner.post(&url).bearer_auth(token)).await?;
+        let resp: Users = Self::json(resp).await?;
+        Ok(resp
+            .users
+            .first()
+            .ok_or(Error::InvalidSkyengData("account has no users"))?
+            .id)
     }
 
+    /// Reuses the cached session unless it's missing, expired or belongs to another
+    /// account, logs in otherwise
     pub async fn login(&self) -> Result<()> {
-        let redirect_url: String = match self
-            .inner
-            .post("https://id.skyeng.ru/frame/login-submit")
-            .form(&self.data_for_login().await?)
-            .send()
-            .await?
-            .json::<LoginResp>()
-            .await?
-        {
-            LoginResp::Success { redirect, success } => {
-                assert!(success);
-                redirect
+        let _guard = self.login_lock.lock().await;
+        if let Some(path) = &self.config.session_file {
+            match Session::load(path) {
+                Some(session) if session.login == self.creds.login && !session.is_expired() => {
+                    log::debug!("reusing session from {}", path.display());
+                    self.creds.set_session(session);
+                    return Ok(());
+                }
+                Some(_) => log::debug!("cached session is stale"),
+                None => {}
             }
-            LoginResp::Failed {
-                message, success, ..
-            } => {
-                assert!(!success);
-                bail!(message)
+        }
+        self.login_with_credentials().await
+    }
+
+    async fn login_with_credentials(&self) -> Result<()> {
+        let resp = self
+            .send_once(
+                self.inner
+                    .post(Self::url(&self.config.id_url, "/frame/login-submit"))
+                    .form(&self.data_for_login().await?),
+            )
+            .await?;
+        let (url, status) = (resp.url().to_string(), resp.status());
+        let payload = resp.text().await?;
+        // failed logins may come with an error status, the body tells more
+        let redirect_url: String = match serde_json::from_str::<LoginResp>(&payload) {
+            Ok(LoginResp::Success { redirect, .. }) => redirect,
+            Ok(LoginResp::Failed { code, .. }) if code.to_lowercase().contains("captcha") => {
+                return Err(Error::CaptchaRequired)
+            }
+            Ok(LoginResp::Failed { message, code, .. }) => {
+                return Err(Error::BadCredentials { message, code })
+            }
+            Err(_) if !status.is_success() => {
+                return Err(Error::Status {
+                    url,
+                    status,
+                    body: payload,
+                })
+            }
+            Err(source) => {
+                return Err(Error::Schema {
+                    url,
+                    payload,
+                    source,
+                })
             }
         };
 
-        self.inner.get(redirect_url).send().await?;
+        self.send(self.inner.get(redirect_url)).await?;
 
         let jwt_resp = self
-            .inner
-            .post("https://id.skyeng.ru/user-api/v1/auth/jwt")
-            .send()
+            .send(
+                self.inner
+                    .post(Self::url(&self.config.id_url, "/user-api/v1/auth/jwt")),
+            )
             .await?;
-        match jwt_resp.headers().get("set-cookie") {
-            None => {
-                bail!("no set-cookie header")
-            }
+        let token = match jwt_resp.headers().get("set-cookie") {
+            None => return Err(Error::InvalidSkyengData("no jwt cookie set")),
             Some(v) => {
-                let cookie = v.to_str()?;
+                let cookie = v
+                    .to_str()
+                    .map_err(|_| Error::InvalidSkyengData("cookie not valid"))?;
                 let (tg, _) = cookie
                     .split_once(';')
                     .ok_or(Error::InvalidSkyengData("cookie not valid"))?;
-                self.creds.set_token(
-                    tg.strip_prefix("token_global=")
-                        .ok_or(Error::InvalidSkyengData("jwt not found in cookies"))?
-                        .to_string(),
-                );
+                tg.strip_prefix("token_global=")
+                    .ok_or(Error::InvalidSkyengData("jwt not found in cookies"))?
+                    .to_string()
             }
-        }
+        };
 
-        self.creds.set_user_id(self.get_user_id().await?);
+        let session = Session {
+            login: self.creds.login.clone(),
+            user_id: self.get_user_id(&token).await?,
+            token,
+        };
+        if let Some(path) = &self.config.session_file {
+            if let Err(e) = session.save(path) {
+                log::warn!("can't cache session: {e:#}");
+            }
+        }
+        self.creds.set_session(session);
 
         Ok(())
     }
 
     async fn data_for_login(&self) -> Result<HashMap<String, String>> {
         let initial_resp = self
-            .inner
-            .get("https://id.skyeng.ru/login")
-            .send()
+            .send(self.inner.get(Self::url(&self.config.id_url, "/login")))
             .await?
             .text()
             .await?;
@@ -148,45 +324,73 @@ impl Client {
                     .unwrap(),
             )
             .next()
-            .unwrap();
+            .ok_or(Error::LoginFormChanged("login form not found"))?;
 
         let mut data = HashMap::new();
         data.insert("username".to_string(), self.creds.login.clone());
         data.insert("password".to_string(), self.creds.password.clone());
         for node in form.children() {
-            let val = node.value();
-            if !val.is_element() {
-                continue;
-            }
-            let inp = node.value().as_element().unwrap();
-            if inp.name() != "input" {
-                continue;
-            }
+            let inp = match node.value().as_element() {
+                Some(el) if el.name() == "input" => el,
+                _ => continue,
+            };
 
-            if inp.attr("type").unwrap() == "hidden" {
-                data.insert(
-                    inp.attr("name").unwrap().to_string(),
-                    inp.attr("value").unwrap().to_string(),
-                );
+            if inp.attr("type") == Some("hidden") {
+                match (inp.attr("name"), inp.attr("value")) {
+                    (Some(name), Some(value)) => {
+                        data.insert(name.to_string(), value.to_string());
+                    }
+                    _ => {
+                        return Err(Error::LoginFormChanged(
+                            "hidden input without name or value",
+                        ))
+                    }
+                }
             }
         }
 
         Ok(data)
     }
 
+    /// Downloads a media file, returns its content and content type.
+    /// Media are served from a CDN and need no authorization.
+    pub async fn download(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let resp = self.send(self.inner.get(url)).await?;
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        Ok((resp.bytes().await?.to_vec(), content_type))
+    }
+
     pub async fn default_wordset(&self) -> Result<DefaultWordset> {
-        Ok(self
-            .put("https://api-words.skyeng.ru/api/for-mobile/v1/wordsets/default.json")
-            .send()
-            .await?
-            .json()
-            .await?)
+        Self::json(
+            self.send_authorized(self.put(Self::url(
+                &self.config.words_api_url,
+                "/api/for-mobile/v1/wordsets/default.json",
+            )))
+            .await?,
+        )
+        .await
     }
 
     pub async fn wordsets_page(&self, page_size: i32, page: i32) -> Result<WordsetsResp> {
-        Ok(self.get(
-            format!("https://api-words.skyeng.ru/api/for-vimbox/v1/wordsets.json?studentId={student_id}&pageSize={page_size}&page={page}", student_id=self.creds.user_id())
-        ).send().await?.json().await?)
+        Self::json(
+            self.send_authorized(
+                self.get(Self::url(
+                    &self.config.words_api_url,
+                    "/api/for-vimbox/v1/wordsets.json",
+                ))
+                .query(&[
+                    ("studentId", self.creds.user_id()?),
+                    ("pageSize", page_size),
+                    ("page", page),
+                ]),
+            )
+            .await?,
+        )
+        .await
     }
 
     pub async fn words_of_wordset(
@@ -195,24 +399,43 @@ impl Client {
         page_size: i32,
         page: i32,
     ) -> Result<WordsResp> {
-        Ok(self
-            .get(format!(
-                r#"https://api-words.skyeng.ru/api/v1/wordsets/{wordset_id}/words.json?
-    studentId={student_id}&
-    wordsetId={wordset_id}&
-    pageSize={page_size}&
-    page={page}&
-    acceptLanguage=ru"#,
-                student_id = self.creds.user_id(),
-            ))
-            .send()
-            .await?
-            .json()
-            .await?)
+        Self::json(
+            self.send_authorized(
+                self.get(Self::url(
+                    &self.config.words_api_url,
+                    &format!("/api/v1/wordsets/{wordset_id}/words.json"),
+                ))
+                .query(&[
+                    ("studentId", self.creds.user_id()?.to_string()),
+                    ("wordsetId", wordset_id.to_string()),
+                    ("pageSize", page_size.to_string()),
+                    ("page", page.to_string()),
+                    ("acceptLanguage", "ru".to_string()),
+                ]),
+            )
+            .await?,
+        )
+        .await
     }
 
     pub async fn meanings(&self, meaning_ids: &[String]) -> Result<Vec<Meaning>> {
-        let joined = meaning_ids.join(",");
-        Ok(self.get(format!(r#"https://dictionary.skyeng.ru/api/for-services/v2/meanings?ids={joined}&acceptLanguage=ru"#)).send().await?.json().await?)
+        Self::json(
+            self.send_authorized(
+                self.get(Self::url(
+                    &self.config.dictionary_url,
+                    "/api/for-services/v2/meanings",
+                ))
+                .query(&[
+                    ("ids", meaning_ids.join(",")),
+                    ("acceptLanguage", "ru".to_string()),
+                ]),
+            )
+            .await?,
+        )
+        .await
     }
 }
+
+fn is_unauthorized(e: &Error) -> bool {
+    matches!(e, Error::Status { status, .. } if *status == StatusCode::UNAUTHORIZED)
+}
@@ -0,0 +1,56 @@
+use reqwest::StatusCode;
+use std::time::Duration;
+use thiserror::Error as TError;
+
+/// Longest piece of a response body kept in error messages
+const EXCERPT_LEN: usize = 200;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(TError, Debug)]
+pub enum Error {
+    #[error("wrong login or password: {message}")]
+    BadCredentials { message: String, code: String },
+    #[error("Skyeng asks to solve a captcha, log in via the browser once and try again")]
+    CaptchaRequired,
+    /// The login page doesn't look as expected, most likely Skyeng has changed it
+    #[error("login form has changed: {0}")]
+    LoginFormChanged(&'static str),
+    #[error("{url} answered {status}: {}", excerpt(.body))]
+    Status {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The response isn't what the models describe, `payload` holds it as received
+    #[error("unexpected response from {url}: {source}")]
+    Schema {
+        url: String,
+        payload: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("session has expired and logging in again didn't help")]
+    SessionExpired,
+    #[error("login and password are required")]
+    MissingCredentials,
+    /// An API method was called before `Client::login`
+    #[error("not logged in yet")]
+    NotLoggedIn,
+    /// Requests with a streamed body can't be sent more than once
+    #[error("request can't be repeated, its body is streamed")]
+    NotRepeatable,
+    #[error("rate limited by Skyeng{}", .retry_after.map(|d| format!(", retry in {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("invalid skyeng data: {0}")]
+    InvalidSkyengData(&'static str),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+fn excerpt(body: &str) -> &str {
+    match body.char_indices().nth(EXCERPT_LEN) {
+        Some((end, _)) => &body[..end],
+        None => body,
+    }
+}
@@ -1,5 +1,14 @@
+#[allow(clippy::module_inception)]
 mod client;
+mod error;
 pub mod models;
+mod rate_limit;
+mod retry;
+mod session;
 
-pub use client::Client;
+pub use client::{Client, ClientConfig};
+pub use error::{Error, Result};
 pub use models::*;
+pub use rate_limit::RateLimit;
+pub use retry::RetryPolicy;
+pub use session::Session;
@@ -67,11 +67,43 @@ pub struct Meaning {
     #[serde(rename(deserialize = "difficultyLevel"))]
     pub difficulty_level: Option<i8>,
     pub text: String,
-    pub translation: TextFieldOnly,
+    pub translation: Translation,
     pub definition: Option<TextFieldOnly>,
     #[serde(rename(deserialize = "isGold3000"))]
     pub is_gold_3000: bool,
     pub examples: Vec<TextFieldOnly>,
+    /// IPA, without slashes
+    #[serde(default)]
+    pub transcription: Option<String>,
+    /// Short code like `n` or `v`
+    #[serde(rename(deserialize = "partOfSpeechCode"), default)]
+    pub part_of_speech_code: Option<String>,
+    /// Pronunciation of the text, may be protocol relative
+    #[serde(rename(deserialize = "soundUrl"), default)]
+    pub sound_url: Option<String>,
+    #[serde(default)]
+    pub images: Vec<Image>,
+    #[serde(rename(deserialize = "alternativeTranslations"), default)]
+    pub alternative_translations: Vec<AlternativeTranslation>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Translation {
+    pub text: String,
+    /// Usage hint shown next to the translation, e.g. "разг."
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Image {
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AlternativeTranslation {
+    pub text: String,
+    pub translation: Translation,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
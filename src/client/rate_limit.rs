@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Requests allowed per second on average and how many may go in a burst
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            per_second: 5.0,
+            burst: 10,
+        }
+    }
+}
+
+/// Token bucket shared by every request of a client
+pub struct RateLimiter {
+    limit: Option<RateLimit>,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// `None` lets every request through immediately
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        let tokens = limit.as_ref().map_or(0.0, |l| f64::from(l.burst.max(1)));
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a token is available and takes it
+    pub async fn acquire(&self) {
+        let limit = match &self.limit {
+            Some(limit) if limit.per_second > 0.0 => limit,
+            _ => return,
+        };
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().expect("rate limiter poisoned");
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                bucket.tokens =
+                    (bucket.tokens + elapsed * limit.per_second).min(f64::from(limit.burst.max(1)));
+                bucket.refilled_at = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / limit.per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
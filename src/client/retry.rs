@@ -0,0 +1,69 @@
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use std::time::{Duration, SystemTime};
+
+/// How many times and how patiently failed requests are repeated
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per request including the first one, 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter, the delay is picked from the upper half of the
+    /// exponential step so concurrent clients don't retry in lockstep
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let step = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        step / 2 + step.mul_f64(fastrand::f64() / 2.0)
+    }
+
+    /// Delay before the next attempt, the server's `Retry-After` wins over the backoff
+    pub fn delay(&self, attempt: u32, resp: Option<&Response>) -> Duration {
+        resp.and_then(retry_after)
+            .unwrap_or_else(|| self.backoff(attempt))
+    }
+}
+
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Connection failures and timeouts, errors of a received response aren't worth repeating
+pub fn is_retryable_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request()
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date
+pub fn retry_after(resp: &Response) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(SystemTime::now())
+            .ok()
+            .or(Some(Duration::ZERO)),
+    }
+}
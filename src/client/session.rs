@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::NamedTempFile;
+
+/// Tokens expiring sooner than this are treated as expired already
+const EXPIRY_LEEWAY_SECS: i64 = 60;
+
+/// Token and user id obtained by logging in, cached between runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub login: String,
+    pub token: String,
+    pub user_id: i32,
+}
+
+impl Session {
+    /// `$XDG_CACHE_HOME/skyeng-words/session.json`, falling back to `~/.cache`
+    pub fn default_path() -> Option<PathBuf> {
+        let cache = match std::env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+        };
+        Some(cache.join("skyeng-words").join("session.json"))
+    }
+
+    /// Reads a cached session, a missing or unreadable cache is no session
+    pub fn load(path: &Path) -> Option<Session> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("can't read session cache {}: {e}", path.display());
+                return None;
+            }
+        };
+        serde_json::from_slice(&content)
+            .map_err(|e| log::warn!("ignoring broken session cache {}: {e}", path.display()))
+            .ok()
+    }
+
+    /// Writes the session readable by the current user only
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)
+            .with_context(|| format!("can't create directory {}", dir.display()))?;
+        // temp files are created with 0600 already
+        let mut temp = NamedTempFile::new_in(dir)?;
+        temp.write_all(&serde_json::to_vec(self)?)?;
+        temp.as_file().sync_all()?;
+        temp.persist(path)
+            .with_context(|| format!("can't save session to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Deletes the cache, returns whether there was one
+    pub fn remove(path: &Path) -> Result<bool> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("can't remove {}", path.display())),
+        }
+    }
+
+    /// The `exp` claim of the JWT, `None` if the token can't be decoded
+    pub fn expires_at(&self) -> Option<i64> {
+        let payload = self.token.split('.').nth(1)?;
+        let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+        serde_json::from_slice::<serde_json::Value>(&payload)
+            .ok()?
+            .get("exp")?
+            .as_i64()
+    }
+
+    /// Tokens without a readable expiry aren't trusted
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time before unix epoch")
+            .as_secs() as i64;
+        !matches!(self.expires_at(), Some(exp) if exp > now + EXPIRY_LEEWAY_SECS)
+    }
+}
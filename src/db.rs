@@ -1,60 +1,948 @@
 use crate::client::models::{Meaning, Wordset};
-use anyhow::{bail, Result};
-use entity::{words, wordsets};
-use once_cell::sync::OnceCell;
+use crate::export::{Entry, ExportOptions};
+use crate::media::StoredFile;
+use crate::search::{self, SearchHit, SearchOptions};
+use anyhow::{bail, Context, Result};
+use entity::{
+    alternative_translations, examples, export_items, exports, media, words, wordset_words,
+    wordsets,
+};
+use migration::{Migrator, MigratorTrait};
+use regex::Regex;
+use sea_orm::sea_query::{Expr, Query};
 use sea_orm::ActiveValue::{NotSet, Set};
 use sea_orm::{
-    ColumnTrait, ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection,
-    EntityTrait, QueryFilter, QueryResult, Statement,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectOptions, ConnectionTrait, Database,
+    DatabaseConnection, DbBackend, EntityTrait, QueryFilter, QueryOrder, Statement,
+    TransactionTrait,
 };
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-static POOL: OnceCell<DatabaseConnection> = OnceCell::new();
+/// Keeps `IN (...)` lists well below SQLite's bound variables limit
+const IDS_CHUNK: usize = 500;
+/// Rows inserted by a single statement, each of them binds a few variables
+const ROWS_CHUNK: usize = 100;
 
-pub async fn init_pool(db_url: &str) {
-    let mut opt = ConnectOptions::from(db_url);
-    opt.max_connections(1).min_connections(1);
-    POOL.set(
-        Database::connect(opt)
-            .await
-            .expect("can't initialize database pool"),
-    )
-    .expect("pool already initialized");
+/// Owns the database connection, every query goes through it.
+///
+/// Cloning is cheap, clones share the connection pool.
+#[derive(Clone)]
+pub struct Store {
+    conn: DatabaseConnection,
 }
 
-fn get_pool() -> &'static DatabaseConnection {
-    POOL.get().expect("db pool not initialized yet")
-}
+impl Store {
+    /// Connects to the database and applies pending migrations
+    pub async fn connect(db_url: &str) -> Result<Self> {
+        let mut opt = ConnectOptions::new(db_url.to_string());
+        if is_sqlite_in_memory(db_url) {
+            // every connection to an in-memory database opens a database of its own
+            opt.max_connections(1).min_connections(1);
+        }
+        let store = Self::new(Database::connect(opt).await?);
+        Migrator::up(&store.conn, None).await?;
+        Ok(store)
+    }
+
+    /// A fresh migrated SQLite database living in memory, meant for tests.
+    ///
+    /// Every call gives a separate database, it's gone once the last clone is dropped.
+    pub async fn in_memory() -> Result<Self> {
+        Self::connect("sqlite::memory:").await
+    }
+
+    /// Wraps a connection to an already migrated database
+    pub fn new(conn: DatabaseConnection) -> Self {
+        Self { conn }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.conn
+    }
+
+    /// Stores words seen for the first time along with their examples and alternatives.
+    ///
+    /// Either every row is saved or none of them is.
+    pub async fn save_new_words(&self, meanings: Vec<Meaning>) -> Result<()> {
+        let examples: Vec<examples::ActiveModel> =
+            meanings.iter().flat_map(make_examples).collect();
+        let alternatives: Vec<alternative_translations::ActiveModel> =
+            meanings.iter().flat_map(make_alternatives).collect();
+        let words: Vec<words::ActiveModel> = meanings.into_iter().map(make_word).collect();
+
+        let txn = self.conn.begin().await?;
+        for chunk in words.chunks(ROWS_CHUNK) {
+            words::Entity::insert_many(chunk.to_vec())
+                .exec(&txn)
+                .await?;
+        }
+        for chunk in examples.chunks(ROWS_CHUNK) {
+            examples::Entity::insert_many(chunk.to_vec())
+                .exec(&txn)
+                .await?;
+        }
+        for chunk in alternatives.chunks(ROWS_CHUNK) {
+            alternative_translations::Entity::insert_many(chunk.to_vec())
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Records which meanings the wordset returned on this sync.
+    ///
+    /// Memberships missing from `meaning_ids` are marked as removed, returned ones are restored.
+    pub async fn sync_ws_membership(&self, wordset_id: i32, meaning_ids: &[i32]) -> Result<()> {
+        let now = now();
+        let fetched: HashSet<i32> = meaning_ids.iter().copied().collect();
+        let existing = wordset_words::Entity::find()
+            .filter(wordset_words::Column::WordsetId.eq(wordset_id))
+            .all(&self.conn)
+            .await?;
+        let known: HashSet<i32> = existing.iter().map(|m| m.meaning_id).collect();
+        let (gone, back): (Vec<_>, Vec<_>) = existing
+            .iter()
+            .filter(|m| m.removed_at.is_none() != fetched.contains(&m.meaning_id))
+            .map(|m| m.meaning_id)
+            .partition(|id| !fetched.contains(id));
+        let new: Vec<wordset_words::ActiveModel> = meaning_ids
+            .iter()
+            .filter(|id| !known.contains(id))
+            .map(|id| wordset_words::ActiveModel {
+                wordset_id: Set(wordset_id),
+                meaning_id: Set(*id),
+                removed_at: Set(None),
+            })
+            .collect();
+
+        let txn = self.conn.begin().await?;
+        for (ids, removed_at) in [(gone, Some(now)), (back, None)] {
+            for chunk in ids.chunks(IDS_CHUNK) {
+                wordset_words::Entity::update_many()
+                    .col_expr(wordset_words::Column::RemovedAt, Expr::value(removed_at))
+                    .filter(wordset_words::Column::WordsetId.eq(wordset_id))
+                    .filter(wordset_words::Column::MeaningId.is_in(chunk.to_vec()))
+                    .exec(&txn)
+                    .await?;
+            }
+        }
+        for chunk in new.chunks(ROWS_CHUNK) {
+            wordset_words::Entity::insert_many(chunk.to_vec())
+                .exec(&txn)
+                .await?;
+        }
+        wordsets::Entity::update_many()
+            .col_expr(wordsets::Column::SyncedAt, Expr::value(now))
+            .filter(wordsets::Column::Id.eq(wordset_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Saves a wordset listed by Skyeng, restoring it if it was marked as removed before
+    pub async fn save_ws(&self, wordset: &Wordset) -> Result<()> {
+        match wordsets::Entity::find_by_id(wordset.id)
+            .one(&self.conn)
+            .await?
+        {
+            Some(stored) => {
+                if stored.name != wordset.title || stored.removed_at.is_some() {
+                    wordsets::ActiveModel {
+                        id: Set(wordset.id),
+                        name: Set(wordset.title.to_owned()),
+                        removed_at: Set(None),
+                        synced_at: NotSet,
+                    }
+                    .update(&self.conn)
+                    .await?;
+                }
+            }
+            None => {
+                wordsets::Entity::insert(wordsets::ActiveModel {
+                    id: Set(wordset.id),
+                    name: Set(wordset.title.to_owned()),
+                    removed_at: Set(None),
+                    synced_at: Set(None),
+                })
+                .exec(&self.conn)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks stored wordsets missing from the fetched ones as removed, returns how many were
+    pub async fn mark_removed_wordsets(&self, fetched_ids: &[i32]) -> Result<u64> {
+        let fetched: HashSet<i32> = fetched_ids.iter().copied().collect();
+        let gone: Vec<i32> = wordsets::Entity::find()
+            .filter(wordsets::Column::RemovedAt.is_null())
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .map(|ws| ws.id)
+            .filter(|id| !fetched.contains(id))
+            .collect();
+
+        let now = now();
+        let mut removed = 0;
+        let txn = self.conn.begin().await?;
+        for chunk in gone.chunks(IDS_CHUNK) {
+            removed += wordsets::Entity::update_many()
+                .col_expr(wordsets::Column::RemovedAt, Expr::value(now))
+                .filter(wordsets::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        }
+        txn.commit().await?;
+        Ok(removed)
+    }
+
+    /// Marks words left without any current wordset as removed and restores the ones that got one back
+    pub async fn refresh_removed_words(&self) -> Result<()> {
+        let active: HashSet<i32> = wordset_words::Entity::find()
+            .find_also_related(wordsets::Entity)
+            .filter(wordset_words::Column::RemovedAt.is_null())
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter(|(_, ws)| ws.as_ref().and_then(|ws| ws.removed_at).is_none())
+            .map(|(m, _)| m.meaning_id)
+            .collect();
+        let (gone, back): (Vec<_>, Vec<_>) = words::Entity::find()
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter(|w| w.removed_at.is_none() != active.contains(&w.id))
+            .map(|w| w.id)
+            .partition(|id| !active.contains(id));
+        if !gone.is_empty() {
+            log::info!("{} words were removed upstream", gone.len());
+        }
+
+        let now = now();
+        let txn = self.conn.begin().await?;
+        for (ids, removed_at) in [(gone, Some(now)), (back, None)] {
+            for chunk in ids.chunks(IDS_CHUNK) {
+                words::Entity::update_many()
+                    .col_expr(words::Column::RemovedAt, Expr::value(removed_at))
+                    .filter(words::Column::Id.is_in(chunk.to_vec()))
+                    .exec(&txn)
+                    .await?;
+            }
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Hard-deletes everything marked as removed
+    pub async fn prune_removed(&self) -> Result<Pruned> {
+        let txn = self.conn.begin().await?;
+        let mut pruned = Pruned::default();
 
-pub async fn save_new_ws_words(meanings: Vec<Meaning>, wordset_id: i32) -> Result<()> {
-    words::Entity::insert_many(
-        meanings
+        let word_ids: Vec<i32> = words::Entity::find()
+            .filter(words::Column::RemovedAt.is_not_null())
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        for chunk in word_ids.chunks(IDS_CHUNK) {
+            examples::Entity::delete_many()
+                .filter(examples::Column::MeaningId.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?;
+            alternative_translations::Entity::delete_many()
+                .filter(alternative_translations::Column::MeaningId.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?;
+            pruned.memberships += wordset_words::Entity::delete_many()
+                .filter(wordset_words::Column::MeaningId.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+            pruned.words += words::Entity::delete_many()
+                .filter(words::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        }
+
+        let wordset_ids: Vec<i32> = wordsets::Entity::find()
+            .filter(wordsets::Column::RemovedAt.is_not_null())
+            .all(&txn)
+            .await?
             .into_iter()
-            .map(|m| make_word(m, wordset_id))
-            .collect::<Vec<words::ActiveModel>>(),
-    )
-    .exec(get_pool())
-    .await?;
-    Ok(())
-}
-
-pub async fn save_ws_if_not_exists(wordset: &Wordset) -> Result<()> {
-    if let Some(_) = wordsets::Entity::find_by_id(wordset.id)
-        .one(get_pool())
-        .await?
+            .map(|ws| ws.id)
+            .collect();
+        if !wordset_ids.is_empty() {
+            pruned.memberships += wordset_words::Entity::delete_many()
+                .filter(wordset_words::Column::WordsetId.is_in(wordset_ids.clone()))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+            pruned.wordsets += wordsets::Entity::delete_many()
+                .filter(wordsets::Column::Id.is_in(wordset_ids))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        }
+        pruned.memberships += wordset_words::Entity::delete_many()
+            .filter(wordset_words::Column::RemovedAt.is_not_null())
+            .exec(&txn)
+            .await?
+            .rows_affected;
+
+        txn.commit().await?;
+        Ok(pruned)
+    }
+
+    /// Applies upstream changes to already stored words, returns ids of the changed ones.
+    ///
+    /// Changed words get a new revision and are exported again by the next incremental export.
+    pub async fn update_changed_words(&self, meanings: Vec<Meaning>) -> Result<Vec<i32>> {
+        let ids: Vec<i32> = meanings.iter().map(|m| m.id).collect();
+        let stored = self.get_words_by_ids(&ids).await?;
+        let mut stored_examples = self.get_examples(&ids).await?;
+        let mut stored_alternatives = self.get_alternatives(&ids).await?;
+        let now = now();
+
+        let txn = self.conn.begin().await?;
+        let mut changed = Vec::new();
+        for mean in meanings {
+            let word = match stored.get(&mean.id) {
+                Some(word) => word,
+                None => continue,
+            };
+            let examples = stored_examples.remove(&mean.id).unwrap_or_default();
+            let alternatives = stored_alternatives.remove(&mean.id).unwrap_or_default();
+            if !is_changed(word, &examples, &alternatives, &mean) {
+                continue;
+            }
+            changed.push(mean.id);
+
+            examples::Entity::delete_many()
+                .filter(examples::Column::MeaningId.eq(mean.id))
+                .exec(&txn)
+                .await?;
+            let new_examples = make_examples(&mean);
+            if !new_examples.is_empty() {
+                examples::Entity::insert_many(new_examples)
+                    .exec(&txn)
+                    .await?;
+            }
+            alternative_translations::Entity::delete_many()
+                .filter(alternative_translations::Column::MeaningId.eq(mean.id))
+                .exec(&txn)
+                .await?;
+            let new_alternatives = make_alternatives(&mean);
+            if !new_alternatives.is_empty() {
+                alternative_translations::Entity::insert_many(new_alternatives)
+                    .exec(&txn)
+                    .await?;
+            }
+            words::ActiveModel {
+                created_at: NotSet,
+                removed_at: NotSet,
+                updated_at: Set(now),
+                revision: Set(word.revision + 1),
+                ..make_word(mean)
+            }
+            .update(&txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(changed)
+    }
+
+    /// Adds a batch to the export history of `target`, words are recorded at their current revision
+    pub async fn record_export(
+        &self,
+        target: &str,
+        format: &str,
+        destination: &str,
+        words: &[words::Model],
+    ) -> Result<exports::Model> {
+        self.record_export_with(
+            target,
+            format,
+            destination,
+            &ExportOptions::default(),
+            words,
+            || Ok(()),
+        )
+        .await
+    }
+
+    /// Records a batch like `record_export`, keeping the options it was written with and
+    /// running `publish` right before the commit.
+    ///
+    /// Nothing is published if recording fails and nothing is recorded if `publish` fails.
+    /// Should the commit fail after a successful `publish`, the words stay new to the target
+    /// and go out again with the next export.
+    pub async fn record_export_with<F>(
+        &self,
+        target: &str,
+        format: &str,
+        destination: &str,
+        options: &ExportOptions,
+        words: &[words::Model],
+        publish: F,
+    ) -> Result<exports::Model>
+    where
+        F: FnOnce() -> Result<()>,
     {
-        return Ok(());
-    };
-    wordsets::Entity::insert(wordsets::ActiveModel {
-        id: Set(wordset.id),
-        name: Set(wordset.title.to_owned()),
-    })
-    .exec(get_pool())
-    .await?;
-    Ok(())
-}
-
-fn make_word(mean: Meaning, wordset_id: i32) -> words::ActiveModel {
+        let txn = self.conn.begin().await?;
+        let export = exports::ActiveModel {
+            id: NotSet,
+            created_at: Set(now()),
+            format: Set(format.to_string()),
+            destination: Set(destination.to_string()),
+            word_count: Set(words.len() as i32),
+            target: Set(target.to_string()),
+            reset_at: Set(None),
+            options: Set(Some(serde_json::to_string(options)?)),
+        }
+        .insert(&txn)
+        .await?;
+        let items: Vec<export_items::ActiveModel> = words
+            .iter()
+            .enumerate()
+            .map(|(position, word)| export_items::ActiveModel {
+                export_id: Set(export.id),
+                meaning_id: Set(word.id),
+                revision: Set(word.revision),
+                position: Set(position as i32),
+            })
+            .collect();
+        for chunk in items.chunks(ROWS_CHUNK) {
+            export_items::Entity::insert_many(chunk.to_vec())
+                .exec(&txn)
+                .await?;
+        }
+        if let Err(err) = publish() {
+            txn.rollback()
+                .await
+                .with_context(|| format!("can't roll back export {} after: {err:#}", export.id))?;
+            return Err(err);
+        }
+        txn.commit().await?;
+        Ok(export)
+    }
+
+    /// Past exports, the latest first
+    pub async fn get_exports(&self, target: Option<&str>) -> Result<Vec<exports::Model>> {
+        let mut query = exports::Entity::find();
+        if let Some(target) = target {
+            query = query.filter(exports::Column::Target.eq(target));
+        }
+        Ok(query
+            .order_by_desc(exports::Column::Id)
+            .all(&self.conn)
+            .await?)
+    }
+
+    /// Makes the next incremental export to `target` include every word again.
+    ///
+    /// Batches stay in the history, returns how many of them no longer count.
+    pub async fn reset_target(&self, target: &str) -> Result<u64> {
+        Ok(exports::Entity::update_many()
+            .col_expr(exports::Column::ResetAt, Expr::value(now()))
+            .filter(exports::Column::Target.eq(target))
+            .filter(exports::Column::ResetAt.is_null())
+            .exec(&self.conn)
+            .await?
+            .rows_affected)
+    }
+
+    /// A past export with its words in the original order, exactly as they were exported.
+    ///
+    /// Fails if any of the words has changed or has been pruned since, they can't be
+    /// written the way they were anymore.
+    pub async fn get_export(&self, id: i32) -> Result<Batch> {
+        let export = match exports::Entity::find_by_id(id).one(&self.conn).await? {
+            Some(export) => export,
+            None => bail!("export {id} not found"),
+        };
+        let items = export_items::Entity::find()
+            .filter(export_items::Column::ExportId.eq(id))
+            .order_by_asc(export_items::Column::Position)
+            .all(&self.conn)
+            .await?;
+        let ids: Vec<i32> = items.iter().map(|item| item.meaning_id).collect();
+        let mut stored = self.get_words_by_ids(&ids).await?;
+
+        let (mut changed, mut pruned) = (0, 0);
+        let mut words = Vec::with_capacity(items.len());
+        for item in &items {
+            match stored.remove(&item.meaning_id) {
+                Some(word) if word.revision == item.revision => words.push(word),
+                Some(_) => changed += 1,
+                None => pruned += 1,
+            }
+        }
+        if changed + pruned > 0 {
+            bail!(
+                "export {id} can't be reproduced, {changed} of its words have changed \
+                 and {pruned} have been pruned since"
+            )
+        }
+        let options = match &export.options {
+            Some(options) => Some(serde_json::from_str(options)?),
+            None => None,
+        };
+        Ok(Batch {
+            export,
+            options,
+            entries: self.load_entries(words).await?,
+        })
+    }
+
+    /// Words passing the filter, ordered by id
+    pub async fn find_words(&self, filter: &WordFilter) -> Result<Vec<Entry>> {
+        self.load_entries(self.find_word_rows(filter).await?).await
+    }
+
+    pub async fn get_unexported_words(
+        &self,
+        target: &str,
+        include_removed: bool,
+    ) -> Result<Vec<Entry>> {
+        self.find_words(&WordFilter {
+            not_exported_to: Some(target.to_string()),
+            include_removed,
+            ..WordFilter::default()
+        })
+        .await
+    }
+
+    pub async fn get_all_words(&self, include_removed: bool) -> Result<Vec<Entry>> {
+        self.find_words(&WordFilter {
+            include_removed,
+            ..WordFilter::default()
+        })
+        .await
+    }
+
+    /// Every word of the wordset, no matter which wordset it was synced with first
+    pub async fn get_wordset_words(
+        &self,
+        wordset_id: i32,
+        include_removed: bool,
+    ) -> Result<Vec<Entry>> {
+        let mut membership = Condition::all().add(wordset_words::Column::WordsetId.eq(wordset_id));
+        if !include_removed {
+            membership = membership.add(wordset_words::Column::RemovedAt.is_null());
+        }
+        self.load_entries(
+            words::Entity::find()
+                .inner_join(wordset_words::Entity)
+                .filter(membership)
+                .filter(not_removed(include_removed))
+                .all(&self.conn)
+                .await?,
+        )
+        .await
+    }
+
+    /// A single word with everything stored about it, removed words included
+    pub async fn get_word(&self, id: i32) -> Result<Entry> {
+        let word = match words::Entity::find_by_id(id).one(&self.conn).await? {
+            Some(word) => word,
+            None => bail!("word {id} not found"),
+        };
+        Ok(self.load_entries(vec![word]).await?.remove(0))
+    }
+
+    /// Batches the word went out with, the latest first, along with the revision exported
+    pub async fn get_word_exports(&self, id: i32) -> Result<Vec<(exports::Model, i32)>> {
+        Ok(export_items::Entity::find()
+            .find_also_related(exports::Entity)
+            .filter(export_items::Column::MeaningId.eq(id))
+            .order_by_desc(export_items::Column::ExportId)
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter_map(|(item, export)| Some((export?, item.revision)))
+            .collect())
+    }
+
+    /// Wordsets ordered by id with the number of their words, unexported ones are counted
+    /// when there's a `target`. Removed words don't count.
+    pub async fn get_wordset_summaries(
+        &self,
+        target: Option<&str>,
+        include_removed: bool,
+    ) -> Result<Vec<WordsetSummary>> {
+        let mut query = wordsets::Entity::find();
+        if !include_removed {
+            query = query.filter(wordsets::Column::RemovedAt.is_null());
+        }
+        let wordsets = query
+            .order_by_asc(wordsets::Column::Id)
+            .all(&self.conn)
+            .await?;
+        let current = self.find_word_ids(&WordFilter::default()).await?;
+        let unexported = match target {
+            Some(target) => {
+                self.find_word_ids(&WordFilter {
+                    not_exported_to: Some(target.to_string()),
+                    ..WordFilter::default()
+                })
+                .await?
+            }
+            None => HashSet::new(),
+        };
+        let memberships = wordset_words::Entity::find()
+            .filter(wordset_words::Column::RemovedAt.is_null())
+            .all(&self.conn)
+            .await?;
+        let mut counts: HashMap<i32, (usize, usize)> = HashMap::new();
+        for membership in memberships {
+            if current.contains(&membership.meaning_id) {
+                let count = counts.entry(membership.wordset_id).or_default();
+                count.0 += 1;
+                count.1 += usize::from(unexported.contains(&membership.meaning_id));
+            }
+        }
+        Ok(wordsets
+            .into_iter()
+            .map(|wordset| {
+                let (words, unexported) = counts.remove(&wordset.id).unwrap_or_default();
+                WordsetSummary {
+                    wordset,
+                    words,
+                    unexported: target.map(|_| unexported),
+                }
+            })
+            .collect())
+    }
+
+    /// Rows of the words passing the filter ordered by id, without anything stored besides them
+    pub async fn find_word_rows(&self, filter: &WordFilter) -> Result<Vec<words::Model>> {
+        Ok(words::Entity::find()
+            .filter(filter.condition())
+            .order_by_asc(words::Column::Id)
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter(|w| filter.matches(w))
+            .collect())
+    }
+
+    async fn find_word_ids(&self, filter: &WordFilter) -> Result<HashSet<i32>> {
+        Ok(self
+            .find_word_rows(filter)
+            .await?
+            .into_iter()
+            .map(|w| w.id)
+            .collect())
+    }
+
+    /// Words containing every term of the query, the best matches first.
+    ///
+    /// Terms match prefixes of words in the text, translation, definition and examples.
+    /// SQLite looks them up in its full-text index, other databases search the words loaded.
+    pub async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchHit>> {
+        let terms = search::terms(query);
+        if terms.is_empty() {
+            bail!("nothing to search for in {query:?}");
+        }
+        if self.conn.get_database_backend() != DbBackend::Sqlite {
+            let entries = self.get_all_words(options.include_removed).await?;
+            return Ok(search::search_entries(entries, &terms, options));
+        }
+
+        let weights: Vec<String> = search::WEIGHTS.iter().map(f64::to_string).collect();
+        let sql = format!(
+            "SELECT words.id AS id, bm25(words_search, {}) AS rank, \
+                snippet(words_search, -1, ?, ?, '…', {}) AS snippet \
+            FROM words_search JOIN words ON words.id = words_search.rowid \
+            WHERE words_search MATCH ? {} \
+            ORDER BY rank LIMIT ?",
+            weights.join(", "),
+            search::SNIPPET_WORDS,
+            if options.include_removed {
+                ""
+            } else {
+                "AND words.removed_at IS NULL"
+            },
+        );
+        let (open, close) = options.highlight.clone();
+        let rows = self
+            .conn
+            .query_all(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                &sql,
+                [
+                    open.into(),
+                    close.into(),
+                    search::fts_query(&terms).into(),
+                    (options.limit as i64).into(),
+                ],
+            ))
+            .await?;
+        let mut found = HashMap::with_capacity(rows.len());
+        let mut ids = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: i32 = row.try_get("", "id")?;
+            found.insert(id, (row.try_get("", "rank")?, row.try_get("", "snippet")?));
+            ids.push(id);
+        }
+        let mut words = self.get_words_by_ids(&ids).await?;
+        let words = ids.iter().filter_map(|id| words.remove(id)).collect();
+        Ok(self
+            .load_entries(words)
+            .await?
+            .into_iter()
+            .filter_map(|entry| {
+                let (rank, snippet) = found.remove(&entry.word.id)?;
+                Some(SearchHit {
+                    entry,
+                    snippet,
+                    rank,
+                })
+            })
+            .collect())
+    }
+
+    async fn load_entries(&self, words: Vec<words::Model>) -> Result<Vec<Entry>> {
+        let ids: Vec<i32> = words.iter().map(|word| word.id).collect();
+        let mut examples = self.get_examples(&ids).await?;
+        let mut alternatives = self.get_alternatives(&ids).await?;
+        let mut wordsets = self.get_wordsets_of(&ids).await?;
+        let media_urls: Vec<String> = words
+            .iter()
+            .flat_map(|word| [word.sound_url.clone(), word.image_url.clone()])
+            .flatten()
+            .collect();
+        let media = self.get_media(&media_urls).await?;
+        let media_of = |url: &Option<String>| url.as_ref().and_then(|url| media.get(url)).cloned();
+        Ok(words
+            .into_iter()
+            .map(|word| Entry {
+                sound: media_of(&word.sound_url),
+                image: media_of(&word.image_url),
+                examples: examples.remove(&word.id).unwrap_or_default(),
+                alternatives: alternatives.remove(&word.id).unwrap_or_default(),
+                wordsets: wordsets.remove(&word.id).unwrap_or_default(),
+                word,
+            })
+            .collect())
+    }
+
+    /// Current wordsets containing each of the given meanings
+    pub async fn get_wordsets_of(
+        &self,
+        meaning_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<wordsets::Model>>> {
+        let mut res: HashMap<i32, Vec<wordsets::Model>> = HashMap::new();
+        for chunk in meaning_ids.chunks(IDS_CHUNK) {
+            let memberships = wordset_words::Entity::find()
+                .find_also_related(wordsets::Entity)
+                .filter(wordset_words::Column::MeaningId.is_in(chunk.to_vec()))
+                .filter(wordset_words::Column::RemovedAt.is_null())
+                .filter(wordsets::Column::RemovedAt.is_null())
+                .order_by_asc(wordset_words::Column::WordsetId)
+                .all(&self.conn)
+                .await?;
+            for (membership, wordset) in memberships {
+                if let Some(wordset) = wordset {
+                    res.entry(membership.meaning_id).or_default().push(wordset);
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// Examples of every given meaning, in their original order
+    pub async fn get_examples(&self, meaning_ids: &[i32]) -> Result<HashMap<i32, Vec<String>>> {
+        let mut res: HashMap<i32, Vec<String>> = HashMap::new();
+        for chunk in meaning_ids.chunks(IDS_CHUNK) {
+            let examples = examples::Entity::find()
+                .filter(examples::Column::MeaningId.is_in(chunk.to_vec()))
+                .order_by_asc(examples::Column::MeaningId)
+                .order_by_asc(examples::Column::Position)
+                .all(&self.conn)
+                .await?;
+            for example in examples {
+                res.entry(example.meaning_id)
+                    .or_default()
+                    .push(example.text);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Alternative translations of every given meaning, in their original order
+    pub async fn get_alternatives(
+        &self,
+        meaning_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<alternative_translations::Model>>> {
+        let mut res: HashMap<i32, Vec<alternative_translations::Model>> = HashMap::new();
+        for chunk in meaning_ids.chunks(IDS_CHUNK) {
+            let alternatives = alternative_translations::Entity::find()
+                .filter(alternative_translations::Column::MeaningId.is_in(chunk.to_vec()))
+                .order_by_asc(alternative_translations::Column::MeaningId)
+                .order_by_asc(alternative_translations::Column::Position)
+                .all(&self.conn)
+                .await?;
+            for alternative in alternatives {
+                res.entry(alternative.meaning_id)
+                    .or_default()
+                    .push(alternative);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Sound and image URLs of every word that isn't removed
+    pub async fn get_media_urls(&self) -> Result<Vec<String>> {
+        let words = words::Entity::find()
+            .filter(words::Column::RemovedAt.is_null())
+            .order_by_asc(words::Column::Id)
+            .all(&self.conn)
+            .await?;
+        let mut seen = HashSet::new();
+        Ok(words
+            .into_iter()
+            .flat_map(|word| [word.sound_url, word.image_url])
+            .flatten()
+            .filter(|url| seen.insert(url.clone()))
+            .collect())
+    }
+
+    /// Downloaded media by their URLs
+    pub async fn get_media(&self, urls: &[String]) -> Result<HashMap<String, media::Model>> {
+        let mut res = HashMap::new();
+        for chunk in urls.chunks(IDS_CHUNK) {
+            let found = media::Entity::find()
+                .filter(media::Column::Url.is_in(chunk.to_vec()))
+                .all(&self.conn)
+                .await?;
+            res.extend(found.into_iter().map(|m| (m.url.clone(), m)));
+        }
+        Ok(res)
+    }
+
+    /// Records a downloaded file, replacing an earlier download of the same URL
+    pub async fn save_media(
+        &self,
+        url: String,
+        file: &StoredFile,
+        content_type: Option<String>,
+    ) -> Result<()> {
+        let exists = media::Entity::find_by_id(url.clone())
+            .one(&self.conn)
+            .await?
+            .is_some();
+        let model = media::ActiveModel {
+            url: Set(url),
+            path: Set(file.path.clone()),
+            size: Set(file.size),
+            checksum: Set(file.checksum.clone()),
+            content_type: Set(content_type),
+            downloaded_at: Set(now()),
+        };
+        if exists {
+            model.update(&self.conn).await?;
+        } else {
+            model.insert(&self.conn).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_ws_id_by_name(&self, name: String) -> Result<i32> {
+        Ok(
+            match wordsets::Entity::find()
+                .filter(wordsets::Column::Name.eq(name))
+                .one(&self.conn)
+                .await?
+            {
+                Some(ws) => ws.id,
+                None => bail!("wordset not found"),
+            },
+        )
+    }
+
+    pub async fn get_words_by_ids(&self, ids: &[i32]) -> Result<HashMap<i32, words::Model>> {
+        let mut res = HashMap::with_capacity(ids.len());
+        for chunk in ids.chunks(IDS_CHUNK) {
+            for word in words::Entity::find()
+                .filter(words::Column::Id.is_in(chunk.to_vec()))
+                .all(&self.conn)
+                .await?
+            {
+                res.insert(word.id, word);
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Pruned {
+    pub words: u64,
+    pub wordsets: u64,
+    pub memberships: u64,
+}
+
+fn is_changed(
+    word: &words::Model,
+    examples: &[String],
+    alternatives: &[alternative_translations::Model],
+    mean: &Meaning,
+) -> bool {
+    word.word_id != mean.word_id
+        || word.difficulty_level != i32::from(mean.difficulty_level.unwrap_or_default())
+        || word.text != mean.text
+        || word.translation != mean.translation.text
+        || word.definition != mean.definition.as_ref().map_or("", |d| d.text.as_str())
+        || word.is_gold_3000 != mean.is_gold_3000
+        || !examples.iter().eq(mean.examples.iter().map(|e| &e.text))
+        || word.transcription != non_empty(mean.transcription.as_deref())
+        || word.part_of_speech != non_empty(mean.part_of_speech_code.as_deref())
+        || word.sound_url != mean.sound_url.as_deref().and_then(absolute_url)
+        || word.image_url != mean.images.first().and_then(|i| absolute_url(&i.url))
+        || word.translation_note != non_empty(mean.translation.note.as_deref())
+        || !alternatives
+            .iter()
+            .map(|a| (a.text.as_str(), a.translation.as_str()))
+            .eq(mean
+                .alternative_translations
+                .iter()
+                .map(|a| (a.text.as_str(), a.translation.text.as_str())))
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Skyeng serves media with protocol relative URLs
+fn absolute_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    match url {
+        "" => None,
+        _ if url.starts_with("//") => Some(format!("https:{url}")),
+        _ => Some(url.to_string()),
+    }
+}
+
+fn make_word(mean: Meaning) -> words::ActiveModel {
+    let now = now();
+    let image_url = mean.images.first().and_then(|i| absolute_url(&i.url));
     words::ActiveModel {
+        transcription: Set(non_empty(mean.transcription.as_deref())),
+        part_of_speech: Set(non_empty(mean.part_of_speech_code.as_deref())),
+        sound_url: Set(mean.sound_url.as_deref().and_then(absolute_url)),
+        image_url: Set(image_url),
+        translation_note: Set(non_empty(mean.translation.note.as_deref())),
         id: Set(mean.id),
         word_id: Set(mean.word_id),
         difficulty_level: Set(mean.difficulty_level.unwrap_or_default().into()),
@@ -62,86 +950,153 @@ fn make_word(mean: Meaning, wordset_id: i32) -> words::ActiveModel {
         translation: Set(mean.translation.text),
         definition: Set(mean.definition.map_or("".to_string(), |t| t.text)),
         is_gold_3000: Set(mean.is_gold_3000),
-        examples: Set(mean
-            .examples
-            .into_iter()
-            .map(|t| t.text)
-            .collect::<Vec<String>>()
-            .join(",")),
-        wordset_id: Set(wordset_id),
-        exported: Set(false),
+        created_at: Set(now),
+        updated_at: Set(now),
+        revision: Set(1),
+        removed_at: Set(None),
     }
 }
 
-pub async fn mark_as_exported(ids: Vec<i32>) -> Result<()> {
-    words::Entity::update_many()
-        .set(words::ActiveModel {
-            id: NotSet,
-            word_id: NotSet,
-            difficulty_level: NotSet,
-            text: NotSet,
-            translation: NotSet,
-            definition: NotSet,
-            is_gold_3000: NotSet,
-            examples: NotSet,
-            wordset_id: NotSet,
-            exported: Set(true),
+fn make_examples(mean: &Meaning) -> Vec<examples::ActiveModel> {
+    mean.examples
+        .iter()
+        .enumerate()
+        .map(|(position, example)| examples::ActiveModel {
+            meaning_id: Set(mean.id),
+            position: Set(position as i32),
+            text: Set(example.text.clone()),
         })
-        .filter(words::Column::Id.is_in(ids))
-        .exec(get_pool())
-        .await?;
-    Ok(())
+        .collect()
 }
 
-pub async fn get_unexported_words() -> Result<Vec<words::Model>> {
-    Ok(words::Entity::find()
-        .filter(words::Column::Exported.eq(false))
-        .all(get_pool())
-        .await?)
+fn make_alternatives(mean: &Meaning) -> Vec<alternative_translations::ActiveModel> {
+    mean.alternative_translations
+        .iter()
+        .enumerate()
+        .map(|(position, alt)| alternative_translations::ActiveModel {
+            meaning_id: Set(mean.id),
+            position: Set(position as i32),
+            text: Set(alt.text.clone()),
+            translation: Set(alt.translation.text.clone()),
+        })
+        .collect()
 }
 
-pub async fn get_all_words() -> Result<Vec<words::Model>> {
-    Ok(words::Entity::find().all(get_pool()).await?)
+/// A past export, ready to be written again
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub export: exports::Model,
+    /// `None` for batches recorded before options were kept
+    pub options: Option<ExportOptions>,
+    pub entries: Vec<Entry>,
 }
 
-pub async fn get_ws_id_by_name(name: String) -> Result<i32> {
-    Ok(
-        match wordsets::Entity::find()
-            .filter(wordsets::Column::Name.eq(name))
-            .one(get_pool())
-            .await?
-        {
-            Some(ws) => ws.id,
-            None => bail!("wordset not found"),
-        },
-    )
-}
-
-pub async fn filter_unexisted_word_ids(found: &Vec<i32>) -> Result<Vec<i32>> {
-    let query_res: Vec<QueryResult> = get_pool()
-        .query_all(Statement::from_string(
-            DatabaseBackend::Sqlite,
-            format!(
-                r#"
-            with cte(id) as
-                 (values {values})
-            select id
-            from cte
-            where id not in (select id from words);
-            "#,
-                values = found
-                    .iter()
-                    .map(|v| format!("({v})"))
-                    .collect::<Vec<String>>()
-                    .join(","),
-            )
-            .to_owned(),
-        ))
-        .await?;
-    let mut res = Vec::with_capacity(query_res.len());
-    for row in query_res.into_iter() {
-        res.push(row.try_get("", "id")?);
+/// A wordset with the number of its words
+#[derive(Debug, Clone)]
+pub struct WordsetSummary {
+    pub wordset: wordsets::Model,
+    pub words: usize,
+    /// Words not exported to the target yet, if a target was given
+    pub unexported: Option<usize>,
+}
+
+/// Which words to pick, every set field narrows the selection down
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    /// Only words not exported to this target yet
+    pub not_exported_to: Option<String>,
+    pub include_removed: bool,
+    /// Words currently in any of these wordsets, empty means any wordset
+    pub wordsets: Vec<i32>,
+    pub min_difficulty: Option<i32>,
+    pub max_difficulty: Option<i32>,
+    pub gold_only: bool,
+    /// Added at or after, unix seconds
+    pub since: Option<i64>,
+    /// Added before, unix seconds
+    pub until: Option<i64>,
+    /// Matched against the text and the translation.
+    /// Databases lack a common regex syntax, so it's applied to the rows loaded.
+    pub pattern: Option<Regex>,
+}
+
+impl WordFilter {
+    fn condition(&self) -> Condition {
+        let mut cond = not_removed(self.include_removed);
+        if let Some(target) = &self.not_exported_to {
+            // a changed word has a revision no export has seen yet
+            cond = cond.add(
+                words::Column::Id.not_in_subquery(
+                    Query::select()
+                        .column(export_items::Column::MeaningId)
+                        .from(export_items::Entity)
+                        .inner_join(
+                            exports::Entity,
+                            Expr::tbl(exports::Entity, exports::Column::Id)
+                                .equals(export_items::Entity, export_items::Column::ExportId),
+                        )
+                        .and_where(exports::Column::Target.eq(target.as_str()))
+                        .and_where(exports::Column::ResetAt.is_null())
+                        .and_where(
+                            Expr::tbl(export_items::Entity, export_items::Column::Revision)
+                                .equals(words::Entity, words::Column::Revision),
+                        )
+                        .to_owned(),
+                ),
+            );
+        }
+        if !self.wordsets.is_empty() {
+            cond = cond.add(
+                words::Column::Id.in_subquery(
+                    Query::select()
+                        .column(wordset_words::Column::MeaningId)
+                        .from(wordset_words::Entity)
+                        .and_where(wordset_words::Column::WordsetId.is_in(self.wordsets.clone()))
+                        .and_where(wordset_words::Column::RemovedAt.is_null())
+                        .to_owned(),
+                ),
+            );
+        }
+        if let Some(min) = self.min_difficulty {
+            cond = cond.add(words::Column::DifficultyLevel.gte(min));
+        }
+        if let Some(max) = self.max_difficulty {
+            cond = cond.add(words::Column::DifficultyLevel.lte(max));
+        }
+        if self.gold_only {
+            cond = cond.add(words::Column::IsGold3000.eq(true));
+        }
+        if let Some(since) = self.since {
+            cond = cond.add(words::Column::CreatedAt.gte(since));
+        }
+        if let Some(until) = self.until {
+            cond = cond.add(words::Column::CreatedAt.lt(until));
+        }
+        cond
     }
 
-    Ok(res)
+    fn matches(&self, word: &words::Model) -> bool {
+        match &self.pattern {
+            Some(re) => re.is_match(&word.text) || re.is_match(&word.translation),
+            None => true,
+        }
+    }
+}
+
+fn not_removed(include_removed: bool) -> Condition {
+    if include_removed {
+        Condition::all()
+    } else {
+        Condition::all().add(words::Column::RemovedAt.is_null())
+    }
+}
+
+fn is_sqlite_in_memory(db_url: &str) -> bool {
+    db_url.starts_with("sqlite:") && (db_url.contains(":memory:") || db_url.contains("mode=memory"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
 }
@@ -0,0 +1,425 @@
+use super::{escape_html, Entry, ExportOptions, Exporter};
+use anyhow::Result;
+use entity::media;
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::{tempfile, NamedTempFile};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Fixed so re-imports keep updating the same note type
+const MODEL_ID: i64 = 1_655_651_000_000;
+const ROOT_DECK_ID: i64 = 1_655_652_000_000;
+/// Per-wordset deck ids are derived from wordset ids within this range
+const WORDSET_DECK_ID_BASE: i64 = 1_655_653_000_000_000;
+/// Note and card ids are these plus an offset below 2^40 derived from the note guid,
+/// the ranges don't overlap
+const NOTE_ID_BASE: i64 = 1_655_654_000_000_000;
+const CARD_ID_BASE: i64 = 1_655_656_000_000_000;
+const ROOT_DECK_NAME: &str = "Skyeng";
+const FIELD_SEPARATOR: &str = "\x1f";
+
+const SCHEMA: &str = r#"
+CREATE TABLE col (
+    id integer primary key,
+    crt integer not null,
+    mod integer not null,
+    scm integer not null,
+    ver integer not null,
+    dty integer not null,
+    usn integer not null,
+    ls integer not null,
+    conf text not null,
+    models text not null,
+    decks text not null,
+    dconf text not null,
+    tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key,
+    guid text not null,
+    mid integer not null,
+    mod integer not null,
+    usn integer not null,
+    tags text not null,
+    flds text not null,
+    sfld integer not null,
+    csum integer not null,
+    flags integer not null,
+    data text not null
+);
+CREATE TABLE cards (
+    id integer primary key,
+    nid integer not null,
+    did integer not null,
+    ord integer not null,
+    mod integer not null,
+    usn integer not null,
+    type integer not null,
+    queue integer not null,
+    due integer not null,
+    ivl integer not null,
+    factor integer not null,
+    reps integer not null,
+    lapses integer not null,
+    left integer not null,
+    odue integer not null,
+    odid integer not null,
+    flags integer not null,
+    data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key,
+    cid integer not null,
+    usn integer not null,
+    ease integer not null,
+    ivl integer not null,
+    lastIvl integer not null,
+    factor integer not null,
+    time integer not null,
+    type integer not null
+);
+CREATE TABLE graves (
+    usn integer not null,
+    oid integer not null,
+    type integer not null
+);
+CREATE INDEX ix_notes_usn on notes (usn);
+CREATE INDEX ix_cards_usn on cards (usn);
+CREATE INDEX ix_revlog_usn on revlog (usn);
+CREATE INDEX ix_cards_nid on cards (nid);
+CREATE INDEX ix_cards_sched on cards (did, queue, due);
+CREATE INDEX ix_revlog_cid on revlog (cid);
+CREATE INDEX ix_notes_csum on notes (csum);
+"#;
+
+const FIELDS: [&str; 8] = [
+    "Text",
+    "Translation",
+    "Definition",
+    "Examples",
+    "Transcription",
+    "PartOfSpeech",
+    "Audio",
+    "Image",
+];
+
+const FRONT_TEMPLATE: &str = r#"<div class="text">{{Text}}</div>"#;
+
+const BACK_TEMPLATE: &str = r#"{{FrontSide}}
+<hr id="answer">
+{{#Transcription}}<div class="transcription">/{{Transcription}}/</div>{{/Transcription}}
+{{#PartOfSpeech}}<div class="pos">{{PartOfSpeech}}</div>{{/PartOfSpeech}}
+{{Audio}}
+<div class="translation">{{Translation}}</div>
+{{#Definition}}<div class="definition">{{Definition}}</div>{{/Definition}}
+{{#Examples}}<div class="examples">{{Examples}}</div>{{/Examples}}
+{{#Image}}<div class="image">{{Image}}</div>{{/Image}}"#;
+
+const CSS: &str = r#".card { font-family: arial; font-size: 20px; text-align: center; }
+.text { font-size: 28px; }
+.transcription { font-family: "Lucida Sans Unicode", "Arial Unicode MS", sans-serif; }
+.pos { color: #999; font-size: 14px; font-style: italic; }
+.definition, .examples { color: #666; font-size: 16px; margin-top: 12px; }"#;
+
+/// Anki package with a dedicated note type and one deck per wordset
+pub struct Anki;
+
+impl Exporter for Anki {
+    fn name(&self) -> &'static str {
+        "anki"
+    }
+
+    fn extension(&self) -> &'static str {
+        "apkg"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn export(
+        &self,
+        entries: &[Entry],
+        writer: &mut dyn Write,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let collection = NamedTempFile::new()?;
+        let media = write_collection(&mut Connection::open(collection.path())?, entries, options)?;
+
+        let mut package = ZipWriter::new(tempfile()?);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        package.start_file("collection.anki2", options)?;
+        io::copy(&mut File::open(collection.path())?, &mut package)?;
+        // media files are stored under their index, the `media` map gives them names back
+        let mut names = BTreeMap::new();
+        for (index, (name, path)) in media.iter().enumerate() {
+            package.start_file(index.to_string(), options)?;
+            io::copy(&mut File::open(path)?, &mut package)?;
+            names.insert(index.to_string(), name);
+        }
+        package.start_file("media", options)?;
+        package.write_all(json!(names).to_string().as_bytes())?;
+
+        let mut package = package.finish()?;
+        package.seek(SeekFrom::Start(0))?;
+        io::copy(&mut package, writer)?;
+        Ok(())
+    }
+}
+
+/// Writes notes of the entries, returns media files they refer to by file name
+fn write_collection(
+    conn: &mut Connection,
+    entries: &[Entry],
+    options: &ExportOptions,
+) -> Result<BTreeMap<String, PathBuf>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let conn = conn.transaction()?;
+    conn.execute_batch(SCHEMA)?;
+
+    let mut decks = BTreeMap::new();
+    decks.insert(1, deck(1, "Default", now));
+    decks.insert(ROOT_DECK_ID, deck(ROOT_DECK_ID, ROOT_DECK_NAME, now));
+    for wordset in entries.iter().flat_map(|e| &e.wordsets) {
+        let id = WORDSET_DECK_ID_BASE + wordset.id as i64;
+        decks
+            .entry(id)
+            .or_insert_with(|| deck(id, &format!("{ROOT_DECK_NAME}::{}", wordset.name), now));
+    }
+
+    let mut models = BTreeMap::new();
+    models.insert(MODEL_ID, model(now));
+
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')",
+        params![
+            now,
+            now * 1000,
+            now * 1000,
+            conf().to_string(),
+            json!(models).to_string(),
+            json!(decks).to_string(),
+            json!({ "1": deck_conf() }).to_string(),
+        ],
+    )?;
+
+    let mut insert_note = conn
+        .prepare("INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ' skyeng ', ?5, ?6, ?7, 0, '')")?;
+    let mut insert_card = conn.prepare(
+        "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4, -1, 0, 0, ?5, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+    )?;
+    let mut media = BTreeMap::new();
+    let mut media_name = |file: Option<&media::Model>| -> Option<String> {
+        let path = options.media_path(file)?;
+        if !path.is_file() {
+            return None;
+        }
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        media.insert(name.clone(), path);
+        Some(name)
+    };
+    for (position, entry) in entries.iter().enumerate() {
+        let word = &entry.word;
+        let audio = media_name(entry.sound.as_ref())
+            .map(|name| format!("[sound:{name}]"))
+            .unwrap_or_default();
+        let image = media_name(entry.image.as_ref())
+            .map(|name| format!("<img src=\"{}\">", escape_html(&name)))
+            .unwrap_or_default();
+        let fields = [
+            escape_html(&word.text),
+            escape_html(&word.translation),
+            escape_html(&word.definition),
+            options
+                .examples(entry)
+                .iter()
+                .map(|e| escape_html(e))
+                .collect::<Vec<String>>()
+                .join(options.examples_separator.as_deref().unwrap_or("<br>")),
+            escape_html(word.transcription.as_deref().unwrap_or_default()),
+            escape_html(entry.part_of_speech().unwrap_or_default()),
+            audio,
+            image,
+        ];
+        let fields = fields.join(FIELD_SEPARATOR);
+        // a card lives in a single deck, a word gets a note in the deck of each of its wordsets
+        let decks: Vec<(Option<i32>, i64)> = match entry.wordsets.as_slice() {
+            [] => vec![(None, ROOT_DECK_ID)],
+            wordsets => wordsets
+                .iter()
+                .map(|ws| (Some(ws.id), WORDSET_DECK_ID_BASE + ws.id as i64))
+                .collect(),
+        };
+        for (wordset_id, deck_id) in decks {
+            let guid = guid(word.id, wordset_id);
+            let offset = id_offset(&guid);
+            insert_note.execute(params![
+                NOTE_ID_BASE + offset,
+                guid,
+                MODEL_ID,
+                now,
+                fields,
+                word.text,
+                checksum(&word.text),
+            ])?;
+            insert_card.execute(params![
+                CARD_ID_BASE + offset,
+                NOTE_ID_BASE + offset,
+                deck_id,
+                now,
+                position as i64
+            ])?;
+        }
+    }
+    drop((insert_note, insert_card));
+    conn.commit()?;
+    Ok(media)
+}
+
+/// Derived from the meaning id and the wordset the note is for, so Anki updates notes
+/// on re-import instead of adding copies
+fn guid(meaning_id: i32, wordset_id: Option<i32>) -> String {
+    match wordset_id {
+        Some(wordset_id) => format!("skyeng-meaning-{meaning_id}-wordset-{wordset_id}"),
+        None => format!("skyeng-meaning-{meaning_id}"),
+    }
+}
+
+/// First 40 bits of the sha1 of the guid
+fn id_offset(guid: &str) -> i64 {
+    let digest = Sha1::digest(guid.as_bytes());
+    digest[..5]
+        .iter()
+        .fold(0, |offset, byte| offset << 8 | i64::from(*byte))
+}
+
+/// First 8 hex digits of the sha1 of the sort field, Anki uses it to find duplicates
+fn checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(field.as_bytes());
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ]))
+}
+
+fn deck(id: i64, name: &str, now: i64) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "desc": "",
+        "conf": 1,
+        "dyn": 0,
+        "collapsed": false,
+        "extendNew": 10,
+        "extendRev": 50,
+        "mod": now,
+        "usn": -1,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "lrnToday": [0, 0],
+        "timeToday": [0, 0],
+    })
+}
+
+fn model(now: i64) -> Value {
+    json!({
+        "id": MODEL_ID,
+        "name": "Skyeng word",
+        "type": 0,
+        "mod": now,
+        "usn": -1,
+        "sortf": 0,
+        "did": ROOT_DECK_ID,
+        "tags": [],
+        "vers": [],
+        "css": CSS,
+        "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+        "latexPost": "\\end{document}",
+        "req": [[0, "all", [0]]],
+        "flds": FIELDS
+            .iter()
+            .enumerate()
+            .map(|(ord, name)| json!({
+                "name": name,
+                "ord": ord,
+                "font": "Arial",
+                "size": 20,
+                "rtl": false,
+                "sticky": false,
+                "media": [],
+            }))
+            .collect::<Vec<Value>>(),
+        "tmpls": [{
+            "name": "Recognition",
+            "ord": 0,
+            "qfmt": FRONT_TEMPLATE,
+            "afmt": BACK_TEMPLATE,
+            "bqfmt": "",
+            "bafmt": "",
+            "did": null,
+        }],
+    })
+}
+
+fn conf() -> Value {
+    json!({
+        "activeDecks": [1],
+        "curDeck": 1,
+        "curModel": MODEL_ID.to_string(),
+        "addToCur": true,
+        "collapseTime": 1200,
+        "dueCounts": true,
+        "estTimes": true,
+        "newBury": true,
+        "newSpread": 0,
+        "nextPos": 1,
+        "sortBackwards": false,
+        "sortType": "noteFld",
+        "timeLim": 0,
+    })
+}
+
+fn deck_conf() -> Value {
+    json!({
+        "id": 1,
+        "name": "Default",
+        "autoplay": true,
+        "dyn": false,
+        "maxTaken": 60,
+        "mod": 0,
+        "usn": 0,
+        "replayq": true,
+        "timer": 0,
+        "new": {
+            "bury": true,
+            "delays": [1, 10],
+            "initialFactor": 2500,
+            "ints": [1, 4, 7],
+            "order": 1,
+            "perDay": 20,
+            "separate": true,
+        },
+        "rev": {
+            "bury": true,
+            "ease4": 1.3,
+            "fuzz": 0.05,
+            "ivlFct": 1,
+            "maxIvl": 36500,
+            "minSpace": 1,
+            "perDay": 100,
+        },
+        "lapse": {
+            "delays": [10],
+            "leechAction": 0,
+            "leechFails": 8,
+            "minInt": 1,
+            "mult": 0,
+        },
+    })
+}
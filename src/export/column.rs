@@ -0,0 +1,116 @@
+use super::{Entry, ExportOptions};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A single field of an exported word, serialized under its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Id,
+    WordId,
+    Text,
+    Translation,
+    Definition,
+    Examples,
+    DifficultyLevel,
+    #[serde(rename = "is_gold_3000")]
+    IsGold3000,
+    Wordset,
+    Transcription,
+    PartOfSpeech,
+    SoundUrl,
+    ImageUrl,
+    TranslationNote,
+    AlternativeTranslations,
+}
+
+impl Column {
+    pub const ALL: [Column; 15] = [
+        Column::Id,
+        Column::WordId,
+        Column::Text,
+        Column::Translation,
+        Column::Definition,
+        Column::Examples,
+        Column::DifficultyLevel,
+        Column::IsGold3000,
+        Column::Wordset,
+        Column::Transcription,
+        Column::PartOfSpeech,
+        Column::SoundUrl,
+        Column::ImageUrl,
+        Column::TranslationNote,
+        Column::AlternativeTranslations,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::WordId => "word_id",
+            Column::Text => "text",
+            Column::Translation => "translation",
+            Column::Definition => "definition",
+            Column::Examples => "examples",
+            Column::DifficultyLevel => "difficulty_level",
+            Column::IsGold3000 => "is_gold_3000",
+            Column::Wordset => "wordset",
+            Column::Transcription => "transcription",
+            Column::PartOfSpeech => "part_of_speech",
+            Column::SoundUrl => "sound_url",
+            Column::ImageUrl => "image_url",
+            Column::TranslationNote => "translation_note",
+            Column::AlternativeTranslations => "alternative_translations",
+        }
+    }
+
+    pub fn value(&self, entry: &Entry, options: &ExportOptions) -> String {
+        let word = &entry.word;
+        match self {
+            Column::Id => word.id.to_string(),
+            Column::WordId => word.word_id.to_string(),
+            Column::Text => word.text.clone(),
+            Column::Translation => word.translation.clone(),
+            Column::Definition => word.definition.clone(),
+            Column::Examples => options.join_examples(entry, "; "),
+            Column::DifficultyLevel => word.difficulty_level.to_string(),
+            Column::IsGold3000 => word.is_gold_3000.to_string(),
+            Column::Wordset => entry
+                .wordsets
+                .iter()
+                .map(|ws| ws.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", "),
+            Column::Transcription => word.transcription.clone().unwrap_or_default(),
+            Column::PartOfSpeech => entry.part_of_speech().unwrap_or_default().to_string(),
+            Column::SoundUrl => word.sound_url.clone().unwrap_or_default(),
+            Column::ImageUrl => word.image_url.clone().unwrap_or_default(),
+            Column::TranslationNote => word.translation_note.clone().unwrap_or_default(),
+            Column::AlternativeTranslations => {
+                entry.alternatives().collect::<Vec<String>>().join("; ")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown column {s}, expected one of: {}",
+                    Column::ALL.map(|c| c.name()).join(", ")
+                )
+            })
+    }
+}
@@ -0,0 +1,232 @@
+use super::{Column, Entry, ExportOptions, Exporter, Quoting};
+use anyhow::{bail, Result};
+use csv::{QuoteStyle, Terminator, WriterBuilder};
+use std::io::Write;
+
+const DEFAULT_COLUMNS: [Column; 4] = [
+    Column::Text,
+    Column::Translation,
+    Column::Definition,
+    Column::Examples,
+];
+
+const UTF8_BOM: &[u8] = "\u{feff}".as_bytes();
+
+/// Delimiter separated text, quoted according to RFC 4180
+pub struct Delimited {
+    name: &'static str,
+    extension: &'static str,
+    delimiter: u8,
+}
+
+impl Delimited {
+    pub fn csv() -> Self {
+        Self {
+            name: "csv",
+            extension: "csv",
+            delimiter: b',',
+        }
+    }
+
+    pub fn tsv() -> Self {
+        Self {
+            name: "tsv",
+            extension: "tsv",
+            delimiter: b'\t',
+        }
+    }
+}
+
+impl Exporter for Delimited {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extension(&self) -> &'static str {
+        self.extension
+    }
+
+    fn export(
+        &self,
+        entries: &[Entry],
+        mut writer: &mut dyn Write,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        if options.bom {
+            writer.write_all(UTF8_BOM)?;
+        }
+        let columns = options.columns.as_deref().unwrap_or(&DEFAULT_COLUMNS);
+        let delimiter = options.delimiter.unwrap_or(self.delimiter);
+
+        let mut csv = WriterBuilder::new()
+            .delimiter(delimiter)
+            .terminator(Terminator::CRLF)
+            .quote_style(match options.quoting {
+                Quoting::Necessary => QuoteStyle::Necessary,
+                Quoting::Always => QuoteStyle::Always,
+                Quoting::Never => QuoteStyle::Never,
+            })
+            .from_writer(&mut writer);
+
+        if options.header {
+            csv.write_record(columns.iter().map(|c| c.name()))?;
+        }
+        for entry in entries {
+            let record: Vec<String> = columns.iter().map(|c| c.value(entry, options)).collect();
+            if options.quoting == Quoting::Never {
+                let special = [delimiter, b'"', b'\n', b'\r'];
+                if let Some((column, _)) = columns
+                    .iter()
+                    .zip(&record)
+                    .find(|(_, value)| value.bytes().any(|b| special.contains(&b)))
+                {
+                    bail!(
+                        "{column} of word {} needs quoting, unquoted it would break the row",
+                        entry.word.id
+                    )
+                }
+            }
+            csv.write_record(&record)?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::{words, wordsets};
+
+    fn entry(id: i32, text: &str, translation: &str, examples: &[&str]) -> Entry {
+        Entry {
+            word: words::Model {
+                id,
+                word_id: id,
+                difficulty_level: 1,
+                text: text.to_string(),
+                translation: translation.to_string(),
+                definition: format!("Definition of {text}"),
+                is_gold_3000: false,
+                created_at: 0,
+                updated_at: 0,
+                revision: 1,
+                removed_at: None,
+                transcription: None,
+                part_of_speech: None,
+                sound_url: None,
+                image_url: None,
+                translation_note: None,
+            },
+            wordsets: vec![wordsets::Model {
+                id: 1,
+                name: "Travel".to_string(),
+                synced_at: None,
+                removed_at: None,
+            }],
+            examples: examples.iter().map(|e| e.to_string()).collect(),
+            alternatives: Vec::new(),
+            sound: None,
+            image: None,
+        }
+    }
+
+    fn export(exporter: Delimited, entries: &[Entry], options: &ExportOptions) -> Result<String> {
+        let mut out = Vec::new();
+        exporter.export(entries, &mut out, options)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn csv_quotes_fields_as_rfc_4180_says() {
+        let entries = [
+            entry(
+                1,
+                "luggage",
+                "багаж",
+                &["Pack your luggage.", "Heavy luggage."],
+            ),
+            entry(
+                2,
+                "a \"quote\"",
+                "цитата, кавычки",
+                &["First line\nsecond line"],
+            ),
+        ];
+        let out = export(Delimited::csv(), &entries, &ExportOptions::default()).unwrap();
+        assert_eq!(
+            out,
+            "text,translation,definition,examples\r\n\
+             luggage,багаж,Definition of luggage,Pack your luggage.; Heavy luggage.\r\n\
+             \"a \"\"quote\"\"\",\"цитата, кавычки\",\"Definition of a \"\"quote\"\"\",\"First line\nsecond line\"\r\n"
+        );
+    }
+
+    #[test]
+    fn tsv_only_quotes_fields_with_tabs() {
+        let entries = [entry(1, "one, two", "раз\tдва", &[])];
+        let out = export(Delimited::tsv(), &entries, &ExportOptions::default()).unwrap();
+        assert_eq!(
+            out.lines().nth(1),
+            Some("one, two\t\"раз\tдва\"\tDefinition of one, two\t")
+        );
+    }
+
+    #[test]
+    fn delimiter_header_and_bom_can_be_changed() {
+        let options = ExportOptions {
+            delimiter: Some(b';'),
+            header: false,
+            bom: true,
+            ..ExportOptions::default()
+        };
+        let entries = [entry(1, "soup", "суп; похлёбка", &[])];
+        let out = export(Delimited::csv(), &entries, &options).unwrap();
+        assert_eq!(
+            out,
+            "\u{feff}soup;\"суп; похлёбка\";Definition of soup;\r\n"
+        );
+    }
+
+    #[test]
+    fn columns_are_written_in_the_requested_order() {
+        let options = ExportOptions {
+            columns: Some(vec![Column::Wordset, Column::Translation, Column::Id]),
+            quoting: Quoting::Always,
+            ..ExportOptions::default()
+        };
+        let entries = [entry(7, "ticket", "билет", &[])];
+        let out = export(Delimited::csv(), &entries, &options).unwrap();
+        assert_eq!(
+            out,
+            "\"wordset\",\"translation\",\"id\"\r\n\"Travel\",\"билет\",\"7\"\r\n"
+        );
+    }
+
+    #[test]
+    fn unquoted_rows_are_rejected_when_a_field_needs_quoting() {
+        let options = ExportOptions {
+            quoting: Quoting::Never,
+            ..ExportOptions::default()
+        };
+        let plain = [entry(1, "soup", "суп", &[])];
+        let out = export(Delimited::csv(), &plain, &options).unwrap();
+        assert_eq!(out.lines().nth(1), Some("soup,суп,Definition of soup,"));
+
+        for broken in [
+            entry(2, "bread", "хлеб, батон", &[]),
+            entry(3, "a \"quote\"", "цитата", &[]),
+            entry(4, "lunch", "обед", &["Line\nbreak"]),
+        ] {
+            let err = export(Delimited::csv(), &[broken], &options).unwrap_err();
+            assert!(err.to_string().contains("needs quoting"), "{err}");
+        }
+        // a comma is fine once it isn't the delimiter
+        let out = export(
+            Delimited::tsv(),
+            &[entry(2, "bread", "хлеб, батон", &[])],
+            &options,
+        );
+        assert!(out.is_ok());
+    }
+}
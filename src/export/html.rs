@@ -0,0 +1,89 @@
+use super::{escape_html, file_url, Column, Entry, ExportOptions, Exporter};
+use anyhow::Result;
+use std::io::Write;
+
+const DEFAULT_COLUMNS: [Column; 6] = [
+    Column::Text,
+    Column::Transcription,
+    Column::PartOfSpeech,
+    Column::Translation,
+    Column::Definition,
+    Column::Examples,
+];
+
+const STYLE: &str = "table { border-collapse: collapse; font-family: sans-serif; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; vertical-align: top; text-align: left; }
+img { max-height: 80px; }";
+
+/// A standalone page with a table of words, downloaded media are linked in place
+pub struct Html;
+
+impl Exporter for Html {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn export(
+        &self,
+        entries: &[Entry],
+        writer: &mut dyn Write,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let columns = options.columns.as_deref().unwrap_or(&DEFAULT_COLUMNS);
+        let with_media = options.media_dir.is_some();
+
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(
+            writer,
+            "<html><head><meta charset=\"utf-8\"><title>Skyeng words</title><style>{STYLE}</style></head><body>"
+        )?;
+        writeln!(writer, "<table>")?;
+        if options.header {
+            write!(writer, "<thead><tr>")?;
+            for column in columns {
+                write!(writer, "<th>{}</th>", column.name())?;
+            }
+            if with_media {
+                write!(writer, "<th>audio</th><th>image</th>")?;
+            }
+            writeln!(writer, "</tr></thead>")?;
+        }
+        writeln!(writer, "<tbody>")?;
+        for entry in entries {
+            write!(writer, "<tr>")?;
+            for column in columns {
+                let value = escape_html(&column.value(entry, options));
+                write!(writer, "<td>{}</td>", value.replace('\n', "<br>"))?;
+            }
+            if with_media {
+                let sound = options.media_path(entry.sound.as_ref());
+                let image = options.media_path(entry.image.as_ref());
+                write!(writer, "<td>")?;
+                if let Some(src) = sound.as_deref().and_then(file_url) {
+                    write!(
+                        writer,
+                        "<audio controls src=\"{}\"></audio>",
+                        escape_html(&src)
+                    )?;
+                }
+                write!(writer, "</td><td>")?;
+                if let Some(src) = image.as_deref().and_then(file_url) {
+                    write!(
+                        writer,
+                        "<img src=\"{}\" alt=\"{}\">",
+                        escape_html(&src),
+                        escape_html(&entry.word.text)
+                    )?;
+                }
+                write!(writer, "</td>")?;
+            }
+            writeln!(writer, "</tr>")?;
+        }
+        writeln!(writer, "</tbody>\n</table>\n</body></html>")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use super::{prepare_output, Destination, Entry, ExportOptions, Exporter};
+use crate::db::Store;
+use anyhow::{bail, Result};
+use entity::exports;
+use std::collections::BTreeMap;
+
+/// Counted under `Preview::per_wordset` for words of no wordset
+pub const NO_WORDSET: &str = "(no wordset)";
+
+/// Words on their way to a destination
+pub struct Job<'a> {
+    pub exporter: &'a dyn Exporter,
+    pub words: Vec<Entry>,
+    pub target: String,
+    pub destination: Destination,
+    pub options: ExportOptions,
+    /// Replace a file already at the destination
+    pub overwrite: bool,
+}
+
+/// What `run` does with a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Write the file and record it as a batch of the target
+    Record,
+    /// Write the file only, the words stay new to the target
+    NoMark,
+    /// Write nothing and record nothing, preview the first `rows` words instead
+    DryRun { rows: usize },
+}
+
+pub enum Outcome {
+    Previewed(Preview),
+    /// The batch is there unless the words weren't recorded
+    Written(Option<exports::Model>),
+}
+
+/// Words a dry run would export
+#[derive(Debug)]
+pub struct Preview {
+    pub words: usize,
+    /// Words per wordset name, a word of several wordsets counts in each of them
+    pub per_wordset: BTreeMap<String, usize>,
+    /// The first words in the order they'd be written
+    pub rows: Vec<Entry>,
+}
+
+impl Preview {
+    pub fn new(words: &[Entry], rows: usize) -> Self {
+        let mut per_wordset: BTreeMap<String, usize> = BTreeMap::new();
+        for entry in words {
+            if entry.wordsets.is_empty() {
+                *per_wordset.entry(NO_WORDSET.to_string()).or_default() += 1;
+            }
+            for wordset in &entry.wordsets {
+                *per_wordset.entry(wordset.name.clone()).or_default() += 1;
+            }
+        }
+        Self {
+            words: words.len(),
+            per_wordset,
+            rows: words.iter().take(rows).cloned().collect(),
+        }
+    }
+}
+
+/// Writes the words of the job to its destination or previews them, see `Mode`
+pub async fn run(db: &Store, job: Job<'_>, mode: Mode) -> Result<Outcome> {
+    if job.words.is_empty() {
+        bail!("found no words for export")
+    }
+    if let Mode::DryRun { rows } = mode {
+        return Ok(Outcome::Previewed(Preview::new(&job.words, rows)));
+    }
+    let exporter = job.exporter;
+    let output = prepare_output(&job.destination, job.overwrite, exporter.is_binary(), |w| {
+        exporter.export(&job.words, w, &job.options)
+    })?;
+    if mode == Mode::NoMark {
+        output.publish()?;
+        return Ok(Outcome::Written(None));
+    }
+    let words: Vec<_> = job.words.into_iter().map(|e| e.word).collect();
+    let batch = db
+        .record_export_with(
+            &job.target,
+            exporter.name(),
+            &job.destination.to_string(),
+            &job.options,
+            &words,
+            || output.publish(),
+        )
+        .await?;
+    Ok(Outcome::Written(Some(batch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::{words, wordsets};
+
+    fn entry(id: i32, wordsets: &[&str]) -> Entry {
+        Entry {
+            word: words::Model {
+                id,
+                word_id: id,
+                difficulty_level: 1,
+                text: format!("word {id}"),
+                translation: "слово".to_string(),
+                definition: String::new(),
+                is_gold_3000: false,
+                created_at: 0,
+                updated_at: 0,
+                revision: 1,
+                removed_at: None,
+                transcription: None,
+                part_of_speech: None,
+                sound_url: None,
+                image_url: None,
+                translation_note: None,
+            },
+            wordsets: wordsets
+                .iter()
+                .enumerate()
+                .map(|(id, name)| wordsets::Model {
+                    id: id as i32,
+                    name: name.to_string(),
+                    synced_at: None,
+                    removed_at: None,
+                })
+                .collect(),
+            examples: Vec::new(),
+            alternatives: Vec::new(),
+            sound: None,
+            image: None,
+        }
+    }
+
+    #[test]
+    fn preview_counts_words_per_wordset() {
+        let words = [
+            entry(1, &["Travel"]),
+            entry(2, &["Travel", "Food"]),
+            entry(3, &[]),
+        ];
+        let preview = Preview::new(&words, 20);
+        assert_eq!(preview.words, 3);
+        assert_eq!(
+            preview.per_wordset.into_iter().collect::<Vec<_>>(),
+            [
+                (NO_WORDSET.to_string(), 1),
+                ("Food".to_string(), 1),
+                ("Travel".to_string(), 2)
+            ]
+        );
+        assert_eq!(preview.rows.len(), 3);
+    }
+
+    #[test]
+    fn preview_keeps_the_first_rows() {
+        let words: Vec<Entry> = (1..=5).map(|id| entry(id, &["Travel"])).collect();
+        let preview = Preview::new(&words, 2);
+        assert_eq!(preview.words, 5);
+        let ids: Vec<i32> = preview.rows.iter().map(|e| e.word.id).collect();
+        assert_eq!(ids, [1, 2]);
+    }
+}
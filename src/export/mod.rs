@@ -0,0 +1,275 @@
+mod anki;
+mod column;
+mod delimited;
+mod html;
+mod job;
+mod output;
+mod xlsx;
+
+use anyhow::Result;
+use entity::{alternative_translations, media, words, wordsets};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub use anki::Anki;
+pub use column::Column;
+pub use delimited::Delimited;
+pub use html::Html;
+pub use job::{run, Job, Mode, Outcome, Preview, NO_WORDSET};
+pub(crate) use output::{keep_permissions, TempFile};
+pub use output::{prepare_output, write_output, Destination, PendingOutput};
+pub use xlsx::Xlsx;
+
+/// A stored word together with everything exporters may render besides the `words` row
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub word: words::Model,
+    /// Every wordset the word belongs to, ordered by id
+    pub wordsets: Vec<wordsets::Model>,
+    pub examples: Vec<String>,
+    pub alternatives: Vec<alternative_translations::Model>,
+    /// Downloaded pronunciation, if any
+    pub sound: Option<media::Model>,
+    pub image: Option<media::Model>,
+}
+
+impl Entry {
+    /// Readable name of the word's part of speech, unknown codes are kept as is
+    pub fn part_of_speech(&self) -> Option<&str> {
+        let code = self.word.part_of_speech.as_deref()?;
+        Some(match code {
+            "n" => "noun",
+            "v" => "verb",
+            "j" => "adjective",
+            "r" => "adverb",
+            "prp" => "preposition",
+            "prn" => "pronoun",
+            "crd" => "cardinal number",
+            "ord" => "ordinal number",
+            "cjc" => "conjunction",
+            "exc" => "interjection",
+            "det" => "determiner",
+            "abb" => "abbreviation",
+            "md" => "modal verb",
+            "ph" => "phrase",
+            "phi" => "idiom",
+            "x" => "particle",
+            _ => code,
+        })
+    }
+
+    /// Alternative translations as `text — translation` pairs
+    pub fn alternatives(&self) -> impl Iterator<Item = String> + '_ {
+        self.alternatives
+            .iter()
+            .map(|a| format!("{} — {}", a.text, a.translation))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quoting {
+    /// Quote fields containing delimiters, quotes or line breaks
+    Necessary,
+    Always,
+    Never,
+}
+
+impl FromStr for Quoting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "necessary" => Ok(Quoting::Necessary),
+            "always" => Ok(Quoting::Always),
+            "never" => Ok(Quoting::Never),
+            _ => Err(format!(
+                "unknown quoting {s}, expected one of: necessary, always, never"
+            )),
+        }
+    }
+}
+
+/// Settings shared by all exporters, each format uses the ones that apply to it.
+///
+/// Batches keep them as JSON so they can be exported again the same way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Columns to write, `None` keeps the format's own layout
+    pub columns: Option<Vec<Column>>,
+    /// Overrides the format's delimiter
+    pub delimiter: Option<u8>,
+    pub quoting: Quoting,
+    pub header: bool,
+    /// Prepend a UTF-8 byte order mark to text output
+    pub bom: bool,
+    /// Export only the first N examples of a word
+    pub examples_limit: Option<usize>,
+    /// Joins examples sharing a single field, overrides the format's default
+    pub examples_separator: Option<String>,
+    /// Directory of downloaded media, `None` leaves media out of the export
+    pub media_dir: Option<PathBuf>,
+}
+
+impl ExportOptions {
+    /// Examples of the entry to export, limited by `examples_limit`
+    pub fn examples<'a>(&self, entry: &'a Entry) -> &'a [String] {
+        let limit = self.examples_limit.unwrap_or(usize::MAX);
+        &entry.examples[..entry.examples.len().min(limit)]
+    }
+
+    pub fn join_examples(&self, entry: &Entry, default_separator: &str) -> String {
+        self.examples(entry).join(
+            self.examples_separator
+                .as_deref()
+                .unwrap_or(default_separator),
+        )
+    }
+
+    /// Local copy of a downloaded file, `None` unless media are exported
+    pub fn media_path(&self, media: Option<&media::Model>) -> Option<PathBuf> {
+        Some(self.media_dir.as_ref()?.join(&media?.path))
+    }
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            delimiter: None,
+            quoting: Quoting::Necessary,
+            header: true,
+            bom: false,
+            examples_limit: None,
+            examples_separator: None,
+            media_dir: None,
+        }
+    }
+}
+
+/// Escapes text for HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `file://` URL of a downloaded file, `None` if it's gone
+fn file_url(path: &Path) -> Option<String> {
+    let path = std::fs::canonicalize(path).ok()?;
+    url::Url::from_file_path(path).ok().map(String::from)
+}
+
+pub trait Exporter: Send + Sync {
+    /// Name the format is selected by, e.g. on the command line
+    fn name(&self) -> &'static str;
+    /// File extension without the leading dot
+    fn extension(&self) -> &'static str;
+    /// Binary formats can't be written to stdout
+    fn is_binary(&self) -> bool {
+        false
+    }
+    fn export(
+        &self,
+        entries: &[Entry],
+        writer: &mut dyn Write,
+        options: &ExportOptions,
+    ) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct Registry {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register(Xlsx)
+            .register(Delimited::csv())
+            .register(Delimited::tsv())
+            .register(Html)
+            .register(Anki);
+        registry
+    }
+
+    /// Adds an exporter, replacing an already registered one with the same name
+    pub fn register<E: Exporter + 'static>(&mut self, exporter: E) -> &mut Self {
+        self.exporters.retain(|e| e.name() != exporter.name());
+        self.exporters.push(Box::new(exporter));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.exporters
+            .iter()
+            .find(|e| e.name() == name)
+            .map(|e| e.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.exporters.iter().map(|e| e.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes nothing, `extension` tells instances apart
+    struct Stub(&'static str);
+
+    impl Exporter for Stub {
+        fn name(&self) -> &'static str {
+            "csv"
+        }
+
+        fn extension(&self) -> &'static str {
+            self.0
+        }
+
+        fn export(&self, _: &[Entry], _: &mut dyn Write, _: &ExportOptions) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn builtin_formats_are_registered() {
+        let registry = Registry::with_builtin();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["xlsx", "csv", "tsv", "html", "anki"]
+        );
+        let anki = registry.get("anki").unwrap();
+        assert_eq!(anki.extension(), "apkg");
+        assert!(anki.is_binary());
+        assert!(!registry.get("tsv").unwrap().is_binary());
+    }
+
+    #[test]
+    fn register_replaces_a_format_with_the_same_name() {
+        let mut registry = Registry::with_builtin();
+        registry.register(Stub("txt"));
+        assert_eq!(registry.get("csv").unwrap().extension(), "txt");
+        assert_eq!(registry.names().filter(|name| *name == "csv").count(), 1);
+
+        registry.register(Stub("dat"));
+        assert_eq!(registry.get("csv").unwrap().extension(), "dat");
+        assert_eq!(registry.names().count(), 5);
+    }
+
+    #[test]
+    fn unknown_formats_are_not_found() {
+        assert!(Registry::new().get("csv").is_none());
+        assert!(Registry::with_builtin().get("pdf").is_none());
+        assert!(Registry::with_builtin().get("CSV").is_none());
+    }
+}
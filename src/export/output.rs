@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+
+/// Random characters in names of temporary files
+const TEMP_SUFFIX_LEN: usize = 6;
+
+pub enum Destination {
+    Stdout,
+    File(PathBuf),
+}
+
+impl Destination {
+    /// Parses a destination argument, an existing directory gets `default_name` appended
+    pub fn parse(destination: &str, default_name: &str) -> Self {
+        match destination {
+            "-" => Destination::Stdout,
+            path => {
+                let path = PathBuf::from(path);
+                if path.is_dir() {
+                    Destination::File(path.join(default_name))
+                } else {
+                    Destination::File(path)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Destination::Stdout => f.write_str("-"),
+            Destination::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Runs `write` against the destination and publishes the result right away
+pub fn write_output<F>(
+    destination: &Destination,
+    overwrite: bool,
+    binary: bool,
+    write: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    prepare_output(destination, overwrite, binary, write)?.publish()
+}
+
+/// Runs `write` against the destination, leaving files to be published later.
+///
+/// Files are written to a temporary sibling, closed and synced, the destination is untouched
+/// until `PendingOutput::publish`. Dropping the pending output discards the file, so the
+/// destination never holds a partially written export. Stdout can't be held back and is
+/// written at once.
+pub fn prepare_output<F>(
+    destination: &Destination,
+    overwrite: bool,
+    binary: bool,
+    write: F,
+) -> Result<PendingOutput>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    match destination {
+        Destination::Stdout => {
+            if binary {
+                bail!("binary formats can't be written to stdout")
+            }
+            let stdout = io::stdout();
+            let mut lock = stdout.lock();
+            write(&mut lock)?;
+            lock.flush()?;
+            Ok(PendingOutput { file: None })
+        }
+        Destination::File(path) => {
+            if !overwrite && path.exists() {
+                bail!("{} already exists", path.display())
+            }
+            let mut temp = TempFile::next_to(path)?;
+            write(&mut temp.file)?;
+            temp.file.flush()?;
+            temp.file.sync_all()?;
+            keep_permissions(&temp.file, path)?;
+            Ok(PendingOutput {
+                file: Some(PendingFile {
+                    temp,
+                    path: path.clone(),
+                    overwrite,
+                }),
+            })
+        }
+    }
+}
+
+/// A written export waiting to be moved into place
+pub struct PendingOutput {
+    file: Option<PendingFile>,
+}
+
+struct PendingFile {
+    temp: TempFile,
+    path: PathBuf,
+    overwrite: bool,
+}
+
+impl PendingOutput {
+    /// Renames the file into place
+    pub fn publish(self) -> Result<()> {
+        let file = match self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        file.temp.persist(&file.path, file.overwrite)
+    }
+}
+
+/// A file written next to its destination and renamed into place once complete.
+///
+/// It's created with the permissions any new file gets under the umask and removed
+/// unless persisted.
+pub(crate) struct TempFile {
+    pub file: fs::File,
+    path: TempPath,
+}
+
+impl TempFile {
+    /// Creates a hidden temporary file in the directory of `path`, making the directory
+    pub fn next_to(path: &Path) -> Result<Self> {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(parent)
+            .with_context(|| format!("can't create directory {}", parent.display()))?;
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("{} is not a file path", path.display()))?
+            .to_string_lossy();
+        loop {
+            let suffix: String = std::iter::repeat_with(fastrand::alphanumeric)
+                .take(TEMP_SUFFIX_LEN)
+                .collect();
+            let temp_path = parent.join(format!(".{file_name}.{suffix}.tmp"));
+            let mut options = fs::OpenOptions::new();
+            options.read(true).write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o666);
+            match options.open(&temp_path) {
+                Ok(file) => {
+                    return Ok(Self {
+                        file,
+                        path: TempPath::from_path(temp_path),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("can't create {}", temp_path.display()))
+                }
+            }
+        }
+    }
+
+    /// Renames the file to `path`, replacing a file there only if `overwrite` is set
+    pub fn persist(self, path: &Path, overwrite: bool) -> Result<()> {
+        drop(self.file);
+        let result = if overwrite {
+            self.path.persist(path)
+        } else {
+            self.path.persist_noclobber(path)
+        };
+        result.with_context(|| format!("can't save {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Gives `file` the permissions of the file it replaces at `path`, if there is one
+pub(crate) fn keep_permissions(file: &fs::File, path: &Path) -> Result<()> {
+    if let Ok(meta) = fs::metadata(path) {
+        file.set_permissions(meta.permissions())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn prepare(path: &Path, overwrite: bool, content: &str) -> Result<PendingOutput> {
+        prepare_output(&Destination::File(path.into()), overwrite, false, |w| {
+            Ok(w.write_all(content.as_bytes())?)
+        })
+    }
+
+    #[test]
+    fn destinations_are_parsed() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Destination::parse("-", "words.csv"),
+            Destination::Stdout
+        ));
+        let file = dir.path().join("mine.csv");
+        assert!(matches!(
+            Destination::parse(file.to_str().unwrap(), "words.csv"),
+            Destination::File(path) if path == file
+        ));
+        // directories get the default name
+        assert!(matches!(
+            Destination::parse(dir.path().to_str().unwrap(), "words.csv"),
+            Destination::File(path) if path == dir.path().join("words.csv")
+        ));
+    }
+
+    #[test]
+    fn files_appear_once_published() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("words.csv");
+        let output = prepare(&path, false, "luggage").unwrap();
+        assert!(!path.exists());
+
+        output.publish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "luggage");
+        assert_eq!(files_in(&dir.path().join("nested")), ["words.csv"]);
+    }
+
+    #[test]
+    fn unpublished_and_failed_outputs_leave_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.csv");
+        drop(prepare(&path, false, "luggage").unwrap());
+        let failed = prepare_output(&Destination::File(path), false, false, |w| {
+            w.write_all(b"lugg")?;
+            bail!("disk full")
+        });
+        assert!(failed.is_err());
+        assert!(files_in(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn existing_files_are_replaced_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.csv");
+        fs::write(&path, "old").unwrap();
+
+        let err = prepare(&path, false, "new").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("{} already exists", path.display())
+        );
+        prepare(&path, true, "new").unwrap().publish().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    #[test]
+    fn files_created_meanwhile_are_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.csv");
+        let output = prepare(&path, false, "new").unwrap();
+        fs::write(&path, "theirs").unwrap();
+
+        assert!(output.publish().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs");
+        assert_eq!(files_in(dir.path()), ["words.csv"]);
+    }
+
+    #[test]
+    fn binary_formats_are_not_written_to_stdout() {
+        let mut called = false;
+        let err = prepare_output(&Destination::Stdout, false, true, |_| {
+            called = true;
+            Ok(())
+        })
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "binary formats can't be written to stdout");
+        assert!(!called);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaced_files_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.csv");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        prepare(&path, true, "new").unwrap().publish().unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_files_get_the_permissions_of_any_new_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.csv");
+        prepare(&path, false, "new").unwrap().publish().unwrap();
+        // a file created the usual way, the umask applies to both
+        let plain = dir.path().join("plain.csv");
+        fs::write(&plain, "plain").unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), mode(&plain));
+    }
+}
@@ -0,0 +1,85 @@
+use super::{file_url, Column, Entry, ExportOptions, Exporter};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, Write};
+use tempfile::Builder as TempBuilder;
+use xlsxwriter::Workbook;
+
+/// Layout understood by Lexilize import
+const DEFAULT_LAYOUT: [(Column, &str); 3] = [
+    (Column::Text, "русский"),
+    (Column::Examples, "examples"),
+    (Column::Translation, "перевод"),
+];
+
+pub struct Xlsx;
+
+impl Exporter for Xlsx {
+    fn name(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn extension(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn export(
+        &self,
+        entries: &[Entry],
+        writer: &mut dyn Write,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let layout: Vec<(Column, &str)> = match &options.columns {
+            Some(columns) => columns.iter().map(|c| (*c, c.name())).collect(),
+            None => DEFAULT_LAYOUT.to_vec(),
+        };
+
+        // xlsxwriter can only write to a path, so build the workbook aside and copy it over
+        let workbook_file = TempBuilder::new().suffix(".xlsx").tempfile()?;
+        let wb = Workbook::new(
+            workbook_file
+                .path()
+                .to_str()
+                .context("temporary path is not valid utf-8")?,
+        );
+
+        let mut sheet = wb.add_worksheet(None)?;
+
+        let mut row = 0;
+        if options.header {
+            for (col, (_, header)) in layout.iter().enumerate() {
+                sheet.write_string(row, col as u16, header, None)?;
+            }
+            row += 1;
+        }
+
+        let with_media = options.media_dir.is_some();
+        if with_media && options.header {
+            sheet.write_string(0, layout.len() as u16, "audio", None)?;
+            sheet.write_string(0, layout.len() as u16 + 1, "image", None)?;
+        }
+
+        for entry in entries {
+            for (col, (column, _)) in layout.iter().enumerate() {
+                sheet.write_string(row, col as u16, &column.value(entry, options), None)?;
+            }
+            if with_media {
+                let files = [entry.sound.as_ref(), entry.image.as_ref()];
+                for (offset, file) in files.into_iter().enumerate() {
+                    if let Some(url) = options.media_path(file).as_deref().and_then(file_url) {
+                        sheet.write_url(row, (layout.len() + offset) as u16, &url, None)?;
+                    }
+                }
+            }
+            row += 1;
+        }
+        wb.close()?;
+
+        io::copy(&mut fs::File::open(workbook_file.path())?, writer)?;
+        Ok(())
+    }
+}
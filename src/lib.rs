@@ -1,5 +1,7 @@
 pub mod client;
 pub mod db;
-pub mod error;
 pub mod export;
+pub mod media;
+pub mod search;
+pub mod stats;
 pub mod sync;
@@ -1,33 +1,100 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDateTime;
 
 mod cli;
-use crate::cli::Format;
+mod view;
+use skyeng_words::client::{self, Client, ClientConfig, Session};
+use skyeng_words::db::{Store, WordFilter};
+use skyeng_words::export::{Destination, Preview, Registry};
+use skyeng_words::media::MediaStore;
+use skyeng_words::search::{SearchHit, SearchOptions};
 use skyeng_words::sync::IdOrName;
-use skyeng_words::{client::Client, db, export, sync};
+use skyeng_words::{export, stats, sync};
+use std::path::PathBuf;
+
+const DEFAULT_FORMAT: &str = "xlsx";
+const NO_MEDIA_DIR: &str = "--media-dir is required unless the database is an SQLite file";
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     env_logger::init();
 
+    if let Err(e) = run().await {
+        eprintln!("error: {e:#}");
+        if let Some(hint) = hint(&e) {
+            eprintln!("{hint}");
+        }
+        std::process::exit(exit_code(&e));
+    }
+}
+
+/// Distinct exit codes let scripts tell a typo in the password from Skyeng being down
+fn exit_code(e: &anyhow::Error) -> i32 {
+    match e.downcast_ref::<client::Error>() {
+        None => 1,
+        Some(client::Error::BadCredentials { .. }) => 10,
+        Some(client::Error::CaptchaRequired) => 11,
+        Some(client::Error::LoginFormChanged(_)) => 12,
+        Some(client::Error::Status { .. }) => 13,
+        Some(client::Error::Schema { .. }) => 14,
+        Some(client::Error::SessionExpired) => 15,
+        Some(client::Error::RateLimited { .. }) => 16,
+        Some(client::Error::Http(_)) => 17,
+        Some(client::Error::InvalidSkyengData(_)) => 18,
+        Some(client::Error::MissingCredentials) => 19,
+        Some(client::Error::NotLoggedIn) => 20,
+        Some(client::Error::NotRepeatable) => 21,
+    }
+}
+
+fn hint(e: &anyhow::Error) -> Option<&'static str> {
+    match e.downcast_ref::<client::Error>()? {
+        client::Error::BadCredentials { .. } => Some("check SKYENG_LOGIN and SKYENG_PASSWORD"),
+        client::Error::MissingCredentials => {
+            Some("pass them as arguments or set SKYENG_LOGIN and SKYENG_PASSWORD")
+        }
+        client::Error::LoginFormChanged(_) | client::Error::InvalidSkyengData(_) => {
+            Some("Skyeng has probably changed its site, please report an issue")
+        }
+        client::Error::Schema { payload, .. } => {
+            log::debug!("received payload: {payload}");
+            Some("Skyeng has probably changed its API, run with RUST_LOG=debug to see the response")
+        }
+        client::Error::SessionExpired => Some("run `logout` and try again"),
+        client::Error::RateLimited { .. } => Some("try again later or lower --requests-per-second"),
+        _ => None,
+    }
+}
+
+async fn run() -> Result<()> {
     dotenv::dotenv()?;
-    let cli = cli::parse();
+    let registry = Registry::with_builtin();
+    let cli = cli::parse(&registry);
 
-    migrate(cli.db_url.as_str()).await?;
-    db::init_pool(cli.db_url.as_str()).await;
+    let db = Store::connect(&cli.db_url).await?;
 
+    let client_config = cli.client_config();
+    let session_file = client_config.session_file.clone();
+    let concurrency = usize::from(cli.http.concurrency);
+    let media_dir = cli.media_dir();
     let get_client = || async {
-        assert!(
-            cli.login.is_some() && cli.password.is_some(),
-            "login and password must be presented"
-        );
-        init_client(cli.login.unwrap(), cli.password.unwrap()).await
+        match (cli.login, cli.password) {
+            (Some(login), Some(password)) => init_client(login, password, client_config).await,
+            _ => Err(client::Error::MissingCredentials.into()),
+        }
     };
     match cli.command {
-        cli::Command::Sync => {
+        cli::Command::Sync { with_media } => {
             let client = get_client().await?;
-            sync::sync(&client).await?;
+            sync::sync(&client, &db, concurrency).await?;
+            if with_media {
+                download_media(&client, &db, media_dir, concurrency).await?;
+            }
         }
-        cli::Command::SyncWordset(id_or_name) => {
+        cli::Command::SyncWordset {
+            wordset: id_or_name,
+            with_media,
+        } => {
             let client = get_client().await?;
             match (id_or_name.id, id_or_name.name) {
                 (Some(_), Some(_)) => {
@@ -37,25 +104,101 @@ async fn main() -> Result<()> {
                     bail!("neither id nor name presented")
                 }
                 (Some(id), None) => {
-                    sync::sync_wordset(&client, IdOrName::Id(id)).await?;
+                    sync::sync_wordset(&client, &db, IdOrName::Id(id), concurrency).await?;
                 }
                 (None, Some(name)) => {
-                    sync::sync_wordset(&client, IdOrName::Name(name)).await?;
+                    sync::sync_wordset(&client, &db, IdOrName::Name(name), concurrency).await?;
                 }
             }
+            if with_media {
+                download_media(&client, &db, media_dir, concurrency).await?;
+            }
+        }
+        cli::Command::Export(export_opts) => {
+            export(&registry, &db, &export_opts, media_dir).await?;
         }
-        cli::Command::Export(export_opts) => match export_opts.format {
-            Format::Xlsx => {
-                export_to_xlsx(export_opts.all).await?;
+        cli::Command::Search {
+            query,
+            limit,
+            include_removed,
+        } => {
+            let options = SearchOptions {
+                limit,
+                include_removed,
+                ..SearchOptions::default()
+            };
+            print_hits(&db.search(&query.join(" "), &options).await?);
+        }
+        cli::Command::Wordsets {
+            command:
+                cli::WordsetsCommand::List {
+                    target,
+                    include_removed,
+                    output,
+                },
+        } => {
+            let target = target.as_deref().unwrap_or(DEFAULT_FORMAT);
+            let summaries = db
+                .get_wordset_summaries(Some(target), include_removed)
+                .await?;
+            view::wordsets(&summaries, output);
+        }
+        cli::Command::Words { command } => match command {
+            cli::WordsCommand::List {
+                not_exported_to,
+                include_removed,
+                filter,
+                output,
+            } => {
+                let filter = WordFilter {
+                    not_exported_to,
+                    include_removed,
+                    wordsets: resolve_wordsets(&db, &filter).await?,
+                    ..filter.word_filter()
+                };
+                view::words(&db.find_words(&filter).await?, output);
+            }
+            cli::WordsCommand::Show { id, output } => {
+                let entry = db.get_word(id).await?;
+                view::word(&entry, &db.get_word_exports(id).await?, output);
             }
         },
+        cli::Command::Stats { target, output } => {
+            let targets = match target {
+                Some(target) => vec![target],
+                None => stats::known_targets(&db).await?,
+            };
+            view::stats(&stats::collect(&db, &targets).await?, output);
+        }
+        cli::Command::History { target } => {
+            print_history(&db.get_exports(target.as_deref()).await?)
+        }
+        cli::Command::Reset { target } => {
+            let batches = db.reset_target(&target).await?;
+            log::info!("reset {batches} exports to {target}");
+        }
+        cli::Command::Logout => match session_file {
+            Some(path) if Session::remove(&path)? => {
+                log::info!("removed session cached in {}", path.display())
+            }
+            _ => log::info!("no cached session"),
+        },
+        cli::Command::Prune => {
+            let pruned = db.prune_removed().await?;
+            log::info!(
+                "pruned {} words, {} wordsets and {} wordset memberships",
+                pruned.words,
+                pruned.wordsets,
+                pruned.memberships
+            );
+        }
     }
 
     Ok(())
 }
 
-async fn init_client(login: String, password: String) -> Result<Client> {
-    let client = Client::new(login, password)?;
+async fn init_client(login: String, password: String, config: ClientConfig) -> Result<Client> {
+    let client = Client::with_config(login, password, config)?;
 
     log::debug!("start login");
     client.login().await?;
@@ -63,23 +206,185 @@ async fn init_client(login: String, password: String) -> Result<Client> {
     Ok(client)
 }
 
-async fn migrate(url: &str) -> Result<()> {
-    use migration::{Migrator, MigratorTrait};
-    let connection = sea_orm::Database::connect(url).await?;
-    Ok(Migrator::up(&connection, None).await?)
+async fn download_media(
+    client: &Client,
+    db: &Store,
+    media_dir: Option<PathBuf>,
+    concurrency: usize,
+) -> Result<()> {
+    let store = MediaStore::new(media_dir.context(NO_MEDIA_DIR)?);
+    let stats = sync::download_media(client, db, &store, concurrency).await?;
+    log::info!(
+        "downloaded {} media files to {}, {} were there already, {} failed",
+        stats.downloaded,
+        store.dir().display(),
+        stats.present,
+        stats.failed
+    );
+    Ok(())
 }
 
-async fn export_to_xlsx(all: bool) -> Result<()> {
-    let words = match all {
-        true => db::get_all_words().await?,
-        false => db::get_unexported_words().await?,
+async fn export(
+    registry: &Registry,
+    db: &Store,
+    opts: &cli::Export,
+    media_dir: Option<PathBuf>,
+) -> Result<()> {
+    let mut options = opts.options();
+    if opts.with_media {
+        options.media_dir = Some(media_dir.context(NO_MEDIA_DIR)?);
+    }
+    let (words, format, target) = match opts.batch {
+        Some(id) => {
+            let batch = db.get_export(id).await?;
+            match batch.options {
+                Some(stored) => options = stored,
+                None => log::warn!(
+                    "export {id} was recorded before options were kept, writing it with defaults"
+                ),
+            }
+            (batch.entries, batch.export.format, batch.export.target)
+        }
+        None => {
+            let format = opts.format.as_deref().unwrap_or(DEFAULT_FORMAT);
+            let target = opts.target.as_deref().unwrap_or(format);
+            let filter = WordFilter {
+                wordsets: resolve_wordsets(db, &opts.filter).await?,
+                ..opts.filter(target)
+            };
+            let words = db.find_words(&filter).await?;
+            (words, format.to_string(), target.to_string())
+        }
     };
+    let exporter = registry
+        .get(&format)
+        .with_context(|| format!("unknown format {format}"))?;
 
-    if words.len() == 0 {
-        bail!("found no words for export")
+    let destination = Destination::parse(
+        &opts.destination,
+        &format!("skyeng-words.{}", exporter.extension()),
+    );
+    let summary = format!("{destination} as {format}, target {target}");
+    let mode = if opts.dry_run {
+        export::Mode::DryRun {
+            rows: opts.preview_rows,
+        }
+    } else if opts.batch.is_some() || opts.no_mark {
+        // re-exporting a batch doesn't make a new one
+        export::Mode::NoMark
+    } else {
+        export::Mode::Record
+    };
+    let job = export::Job {
+        exporter,
+        words,
+        target,
+        destination,
+        options,
+        overwrite: opts.force,
+    };
+    match export::run(db, job, mode).await? {
+        export::Outcome::Previewed(preview) => {
+            println!("{} words would be written to {summary}", preview.words);
+            print_preview(&preview);
+        }
+        export::Outcome::Written(Some(batch)) => log::info!(
+            "exported {} words to {} as batch {}",
+            batch.word_count,
+            batch.target,
+            batch.id
+        ),
+        export::Outcome::Written(None) => {
+            log::info!("exported words to {summary} without recording them")
+        }
     }
-    export::export_to_xlsx("temp.xlsx", &words)?;
-    db::mark_as_exported(words.iter().map(|w| w.id).collect()).await?;
 
     Ok(())
 }
+
+async fn resolve_wordsets(db: &Store, filter: &cli::Filter) -> Result<Vec<i32>> {
+    let mut ids = Vec::with_capacity(filter.wordsets.len());
+    for wordset in &filter.wordsets {
+        ids.push(wordset.clone().resolve(db).await?);
+    }
+    Ok(ids)
+}
+
+/// Words per wordset and the first words
+fn print_preview(preview: &Preview) {
+    println!();
+    println!("{:>6}  WORDSET", "WORDS");
+    for (name, count) in &preview.per_wordset {
+        println!("{count:>6}  {name}");
+    }
+
+    println!();
+    println!(
+        "{:>8}  {:<24}  {:<32}  WORDSETS",
+        "ID", "TEXT", "TRANSLATION"
+    );
+    for entry in &preview.rows {
+        let wordsets: Vec<&str> = entry.wordsets.iter().map(|ws| ws.name.as_str()).collect();
+        println!(
+            "{:>8}  {:<24}  {:<32}  {}",
+            entry.word.id,
+            truncate(&entry.word.text, 24),
+            truncate(&entry.word.translation, 32),
+            wordsets.join(", ")
+        );
+    }
+    if preview.words > preview.rows.len() {
+        println!(
+            "{:>8}  and {} more",
+            "…",
+            preview.words - preview.rows.len()
+        );
+    }
+}
+
+/// Cuts `s` to `width` characters, marking the cut with an ellipsis
+fn truncate(s: &str, width: usize) -> String {
+    match s.char_indices().nth(width) {
+        Some(_) => {
+            let kept: String = s.chars().take(width.saturating_sub(1)).collect();
+            format!("{kept}…")
+        }
+        None => s.to_string(),
+    }
+}
+
+/// Every hit takes two lines, the word and the snippet of its best matching field
+fn print_hits(hits: &[SearchHit]) {
+    for hit in hits {
+        let word = &hit.entry.word;
+        println!("{:>8}  {} — {}", word.id, word.text, word.translation);
+        // examples are kept one per line
+        println!("{:>8}  {}", "", hit.snippet.replace('\n', " / "));
+    }
+}
+
+fn print_history(exports: &[entity::exports::Model]) {
+    println!(
+        "{:>5}  {:<16}  {:<12}  {:<8}  {:>6}  DESTINATION",
+        "ID", "DATE", "TARGET", "FORMAT", "WORDS"
+    );
+    for export in exports {
+        let date = NaiveDateTime::from_timestamp_opt(export.created_at, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let destination = match export.destination.as_str() {
+            "" => "?",
+            "-" => "stdout",
+            path => path,
+        };
+        let reset = if export.reset_at.is_some() {
+            " (reset)"
+        } else {
+            ""
+        };
+        println!(
+            "{:>5}  {:<16}  {:<12}  {:<8}  {:>6}  {destination}{reset}",
+            export.id, date, export.target, export.format, export.word_count
+        );
+    }
+}
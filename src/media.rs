@@ -0,0 +1,114 @@
+use crate::export::{keep_permissions, TempFile};
+use anyhow::Result;
+use entity::media;
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Downloaded sounds and images, stored under their checksum so a file shared by
+/// several words is kept once
+pub struct MediaStore {
+    dir: PathBuf,
+}
+
+/// Where `MediaStore::store` has put a file
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    /// Relative to the store's directory, always `/` separated
+    pub path: String,
+    pub checksum: String,
+    pub size: i64,
+}
+
+impl MediaStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `media` next to the file of an SQLite database, `None` for other databases
+    pub fn default_dir(db_url: &str) -> Option<PathBuf> {
+        let path = db_url
+            .strip_prefix("sqlite://")
+            .or_else(|| db_url.strip_prefix("sqlite:"))?;
+        let path = path.split('?').next().unwrap_or_default();
+        if path.is_empty() || path == ":memory:" {
+            return None;
+        }
+        Some(
+            Path::new(path)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+                .join("media"),
+        )
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path_of(&self, media: &media::Model) -> PathBuf {
+        self.dir.join(&media.path)
+    }
+
+    /// Whether the file is on disk with the recorded size
+    pub fn contains(&self, media: &media::Model) -> bool {
+        fs::metadata(self.path_of(media))
+            .map(|meta| meta.is_file() && meta.len() as i64 == media.size)
+            .unwrap_or(false)
+    }
+
+    /// Writes `content` under its checksum unless the same content is there already
+    pub fn store(&self, content: &[u8], extension: &str) -> Result<StoredFile> {
+        let checksum = format!("{:x}", Sha1::digest(content));
+        // two levels keep directories small for large collections
+        let path = format!("{}/{checksum}.{extension}", &checksum[..2]);
+        let stored = StoredFile {
+            path,
+            checksum,
+            size: content.len() as i64,
+        };
+
+        let full_path = self.dir.join(&stored.path);
+        if matches!(fs::metadata(&full_path), Ok(meta) if meta.len() as i64 == stored.size) {
+            return Ok(stored);
+        }
+        let mut temp = TempFile::next_to(&full_path)?;
+        temp.file.write_all(content)?;
+        keep_permissions(&temp.file, &full_path)?;
+        temp.persist(&full_path, true)?;
+        Ok(stored)
+    }
+}
+
+/// Extension for a downloaded file, taken from the URL or guessed from the content type
+pub fn extension(url: &str, content_type: Option<&str>) -> String {
+    let from_url = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| {
+            (1..=5).contains(&ext.len()) && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    if let Some(ext) = from_url {
+        return ext;
+    }
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(str::trim)
+        .unwrap_or_default();
+    match essence {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/ogg" => "ogg",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "bin",
+    }
+    .to_string()
+}
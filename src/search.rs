@@ -0,0 +1,182 @@
+use crate::export::Entry;
+
+/// Weights of the text, translation, definition and examples when ranking matches
+pub const WEIGHTS: [f64; 4] = [10.0, 5.0, 2.0, 1.0];
+/// Words a snippet is cut to
+pub const SNIPPET_WORDS: usize = 12;
+const ELLIPSIS: &str = "…";
+
+/// How many words `Store::search` finds and how it marks matches
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub limit: usize,
+    pub include_removed: bool,
+    /// Put before and after every match in snippets
+    pub highlight: (String, String),
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            limit: 20,
+            include_removed: false,
+            highlight: ("[".to_string(), "]".to_string()),
+        }
+    }
+}
+
+/// A word found by `Store::search`
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub entry: Entry,
+    /// Part of the best matching field with the matches highlighted
+    pub snippet: String,
+    /// Lower is better, only comparable within a single search
+    pub rank: f64,
+}
+
+/// Lowercase words of a query, punctuation and full-text query syntax are dropped
+pub fn terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// FTS5 query matching words that contain every term as a prefix of some word
+pub fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{term}\"*"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Searches words loaded beforehand, what `Store::search` does without a full-text index.
+///
+/// Terms match prefixes of words like they do with the index, but nothing is stemmed.
+/// Hits come ranked, words ranked the same keep their order.
+pub fn search_entries(
+    entries: Vec<Entry>,
+    terms: &[String],
+    options: &SearchOptions,
+) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let (rank, snippet) = rank_entry(&entry, terms, &options.highlight)?;
+            Some(SearchHit {
+                entry,
+                snippet,
+                rank,
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| a.rank.total_cmp(&b.rank));
+    hits.truncate(options.limit);
+    hits
+}
+
+/// Rank and snippet of a word containing every term, weighted matches count negative
+fn rank_entry(
+    entry: &Entry,
+    terms: &[String],
+    highlight: &(String, String),
+) -> Option<(f64, String)> {
+    let examples = entry.examples.join("\n");
+    let fields = [
+        entry.word.text.as_str(),
+        entry.word.translation.as_str(),
+        entry.word.definition.as_str(),
+        examples.as_str(),
+    ];
+    let matches: Vec<usize> = fields
+        .iter()
+        .map(|field| {
+            tokens(field)
+                .filter(|token| terms.iter().any(|term| token.lower.starts_with(term)))
+                .count()
+        })
+        .collect();
+    let all_found = terms.iter().all(|term| {
+        fields
+            .iter()
+            .any(|field| tokens(field).any(|token| token.lower.starts_with(term)))
+    });
+    if !all_found {
+        return None;
+    }
+    let weighted: Vec<f64> = matches
+        .iter()
+        .zip(WEIGHTS)
+        .map(|(count, weight)| *count as f64 * weight)
+        .collect();
+    let best = (0..fields.len())
+        .max_by(|a, b| weighted[*a].total_cmp(&weighted[*b]).then(b.cmp(a)))
+        .expect("there are fields");
+    Some((
+        -weighted.iter().sum::<f64>(),
+        snippet(fields[best], terms, highlight),
+    ))
+}
+
+struct Token {
+    start: usize,
+    end: usize,
+    lower: String,
+}
+
+/// Alphanumeric runs of the text with their byte offsets
+fn tokens(text: &str) -> impl Iterator<Item = Token> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+        while let Some((i, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = *i;
+                break;
+            }
+            chars.next();
+        }
+        Some(Token {
+            start,
+            end,
+            lower: text[start..end].to_lowercase(),
+        })
+    })
+}
+
+/// Up to `SNIPPET_WORDS` words of the field starting a little before the first match
+fn snippet(field: &str, terms: &[String], (open, close): &(String, String)) -> String {
+    let tokens: Vec<Token> = tokens(field).collect();
+    let is_match = |token: &Token| terms.iter().any(|term| token.lower.starts_with(term));
+    let first = tokens.iter().position(is_match).unwrap_or(0);
+    let from = first
+        .saturating_sub(2)
+        .min(tokens.len().saturating_sub(SNIPPET_WORDS));
+    let shown = &tokens[from..tokens.len().min(from + SNIPPET_WORDS)];
+
+    let mut res = String::new();
+    if from > 0 {
+        res.push_str(ELLIPSIS);
+    }
+    let mut pos = shown.first().map_or(0, |token| token.start);
+    for token in shown {
+        res.push_str(&field[pos..token.start]);
+        if is_match(token) {
+            res.push_str(open);
+            res.push_str(&field[token.start..token.end]);
+            res.push_str(close);
+        } else {
+            res.push_str(&field[token.start..token.end]);
+        }
+        pos = token.end;
+    }
+    match tokens.get(from + shown.len()) {
+        Some(_) => res.push_str(ELLIPSIS),
+        None => res.push_str(&field[pos..]),
+    }
+    res
+}
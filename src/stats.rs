@@ -0,0 +1,158 @@
+use crate::db::{Store, WordFilter};
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Words on the Gold 3000 list
+pub const GOLD_3000: usize = 3000;
+
+/// How the vocabulary looks, removed words don't count anywhere
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub words: usize,
+    pub wordsets: Vec<WordsetWords>,
+    /// Levels with at least one word, ascending
+    pub difficulty: Vec<LevelWords>,
+    pub gold_3000: Gold,
+    /// Every week from the one the first word was added in to the latest one
+    pub weeks: Vec<Week>,
+    pub targets: Vec<TargetProgress>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordsetWords {
+    pub id: i32,
+    pub name: String,
+    pub words: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelWords {
+    pub level: i32,
+    pub words: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Gold {
+    /// Distinct words, several meanings of a word count once
+    pub words: usize,
+    /// Part of the list, from 0 to 1
+    pub coverage: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Week {
+    /// Monday, YYYY-MM-DD
+    pub start: String,
+    pub added: usize,
+    /// Words added up to the end of the week
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetProgress {
+    pub target: String,
+    pub exported: usize,
+    /// Words new to the target, changed ones included
+    pub pending: usize,
+}
+
+/// Gathers statistics of the stored words, export progress is reported for each of `targets`
+pub async fn collect(db: &Store, targets: &[String]) -> Result<Stats> {
+    let words = db.find_word_rows(&WordFilter::default()).await?;
+
+    let mut levels: BTreeMap<i32, usize> = BTreeMap::new();
+    for word in &words {
+        *levels.entry(word.difficulty_level).or_default() += 1;
+    }
+    let gold: HashSet<i32> = words
+        .iter()
+        .filter(|w| w.is_gold_3000)
+        .map(|w| w.word_id)
+        .collect();
+
+    let mut progress = Vec::with_capacity(targets.len());
+    for target in targets {
+        let pending = db
+            .find_word_rows(&WordFilter {
+                not_exported_to: Some(target.clone()),
+                ..WordFilter::default()
+            })
+            .await?
+            .len();
+        progress.push(TargetProgress {
+            target: target.clone(),
+            exported: words.len() - pending,
+            pending,
+        });
+    }
+
+    Ok(Stats {
+        words: words.len(),
+        wordsets: db
+            .get_wordset_summaries(None, false)
+            .await?
+            .into_iter()
+            .map(|summary| WordsetWords {
+                id: summary.wordset.id,
+                name: summary.wordset.name,
+                words: summary.words,
+            })
+            .collect(),
+        difficulty: levels
+            .into_iter()
+            .map(|(level, words)| LevelWords { level, words })
+            .collect(),
+        gold_3000: Gold {
+            words: gold.len(),
+            coverage: gold.len() as f64 / GOLD_3000 as f64,
+        },
+        weeks: weeks(words.iter().map(|w| w.created_at)),
+        targets: progress,
+    })
+}
+
+/// Targets found in the export history, by name
+pub async fn known_targets(db: &Store) -> Result<Vec<String>> {
+    let targets: BTreeSet<String> = db
+        .get_exports(None)
+        .await?
+        .into_iter()
+        .map(|export| export.target)
+        .collect();
+    Ok(targets.into_iter().collect())
+}
+
+/// Words added per week, weeks without any are kept so growth reads off evenly
+fn weeks(added_at: impl Iterator<Item = i64>) -> Vec<Week> {
+    let mut per_week: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for timestamp in added_at {
+        if let Some(time) = NaiveDateTime::from_timestamp_opt(timestamp, 0) {
+            *per_week.entry(week_start(time.date())).or_default() += 1;
+        }
+    }
+    let (first, last) = match (per_week.keys().next(), per_week.keys().last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return Vec::new(),
+    };
+
+    let mut res = Vec::new();
+    let mut total = 0;
+    let mut start = first;
+    while start <= last {
+        let added = per_week.get(&start).copied().unwrap_or_default();
+        total += added;
+        res.push(Week {
+            start: start.format("%Y-%m-%d").to_string(),
+            added,
+            total,
+        });
+        start += Duration::weeks(1);
+    }
+    res
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday().into())
+}
@@ -1,78 +1,226 @@
-use crate::client::*;
-use crate::db;
-use anyhow::Result;
+use crate::client::{self, *};
+use crate::db::Store;
+use crate::media::{self, MediaStore};
+use anyhow::{Context, Result};
+use futures::future::try_join_all;
+use futures::stream::{self, StreamExt};
 use std::collections::HashSet;
+use std::convert::Infallible;
 use std::future::Future;
+use std::str::FromStr;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
-pub async fn sync(client: &Client) -> Result<()> {
+/// Meanings requested at once
+const MEANINGS_CHUNK: usize = 50;
+
+/// Syncs every wordset of the account.
+///
+/// Wordsets are fetched in parallel with at most `concurrency` requests in flight across
+/// all of them. Fetched wordsets are saved one by one in the order Skyeng lists them,
+/// so the result doesn't depend on response timings.
+pub async fn sync(client: &Client, db: &Store, concurrency: usize) -> Result<()> {
     log::info!("start fetching wordsets");
     let wordsets = get_wordsets(client).await?;
     log::info!("got {} wordsets", wordsets.len());
 
-    for (i, ws) in wordsets.into_iter().enumerate() {
-        log::info!("{num} wordset started", num = i + 1);
-        db::save_ws_if_not_exists(&ws).await?;
-        sync_wordset(client, IdOrName::Id(ws.id)).await?;
+    let removed = db
+        .mark_removed_wordsets(&wordsets.iter().map(|ws| ws.id).collect::<Vec<i32>>())
+        .await?;
+    if removed > 0 {
+        log::info!("{removed} wordsets were removed upstream");
+    }
+
+    let limit = Semaphore::new(concurrency.max(1));
+    let limit = &limit;
+    let mut fetched = stream::iter(wordsets)
+        .map(|ws| async move {
+            let meanings = fetch_wordset_meanings(client, ws.id, limit).await?;
+            Ok::<_, anyhow::Error>((ws, meanings))
+        })
+        .buffered(concurrency.max(1))
+        .enumerate();
+    while let Some((i, res)) = fetched.next().await {
+        let (ws, meanings) = res?;
+        log::info!("{num} wordset fetched", num = i + 1);
+        db.save_ws(&ws).await?;
+        save_wordset_meanings(db, ws.id, meanings).await?;
     }
+    db.refresh_removed_words().await?;
 
     Ok(())
 }
 
+#[derive(Debug, Default)]
+pub struct MediaStats {
+    pub downloaded: usize,
+    /// Already on disk
+    pub present: usize,
+    pub failed: usize,
+}
+
+/// Downloads sounds and images of stored words that aren't in `store` yet.
+///
+/// A file that can't be downloaded is logged and skipped, the next run retries it.
+pub async fn download_media(
+    client: &Client,
+    db: &Store,
+    store: &MediaStore,
+    concurrency: usize,
+) -> Result<MediaStats> {
+    let urls = db.get_media_urls().await?;
+    let known = db.get_media(&urls).await?;
+    let mut stats = MediaStats::default();
+    let missing: Vec<String> = urls
+        .into_iter()
+        .filter(|url| match known.get(url) {
+            Some(file) if store.contains(file) => {
+                stats.present += 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    log::info!("downloading {} media files", missing.len());
+
+    let mut downloads = stream::iter(missing)
+        .map(|url| async move {
+            let res = client.download(&url).await;
+            (url, res)
+        })
+        .buffer_unordered(concurrency.max(1));
+    while let Some((url, res)) = downloads.next().await {
+        match res {
+            Ok((content, content_type)) => {
+                let extension = media::extension(&url, content_type.as_deref());
+                let file = store.store(&content, &extension)?;
+                db.save_media(url, &file, content_type).await?;
+                stats.downloaded += 1;
+            }
+            Err(e) => {
+                log::warn!("can't download {url}: {e}");
+                stats.failed += 1;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+#[derive(Debug, Clone)]
 pub enum IdOrName {
     Id(i32),
     Name(String),
 }
 
-pub async fn sync_wordset(client: &Client, ws_id_or_name: IdOrName) -> Result<()> {
-    let ws_id = match ws_id_or_name {
-        IdOrName::Id(id) => id,
-        IdOrName::Name(name) => db::get_ws_id_by_name(name).await?,
-    };
-    log::info!("start fetching words");
-    let words = fetch_until_completed(|ps, p| client.words_of_wordset(ws_id, ps, p)).await?;
+/// Numbers are taken for ids, anything else for a name
+impl FromStr for IdOrName {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(id) => IdOrName::Id(id),
+            Err(_) => IdOrName::Name(s.to_string()),
+        })
+    }
+}
+
+impl IdOrName {
+    pub async fn resolve(self, db: &Store) -> Result<i32> {
+        match self {
+            IdOrName::Id(id) => Ok(id),
+            IdOrName::Name(name) => db.get_ws_id_by_name(name).await,
+        }
+    }
+}
+
+pub async fn sync_wordset(
+    client: &Client,
+    db: &Store,
+    ws_id_or_name: IdOrName,
+    concurrency: usize,
+) -> Result<()> {
+    let ws_id = ws_id_or_name.resolve(db).await?;
+    let wordset = get_wordsets(client)
+        .await?
+        .into_iter()
+        .find(|ws| ws.id == ws_id)
+        .with_context(|| format!("wordset {ws_id} not found on Skyeng"))?;
+    let limit = Semaphore::new(concurrency.max(1));
+    let meanings = fetch_wordset_meanings(client, ws_id, &limit).await?;
+    // a wordset synced by id may be new, its row comes before its words
+    db.save_ws(&wordset).await?;
+    save_wordset_meanings(db, ws_id, meanings).await?;
+    db.refresh_removed_words().await
+}
+
+/// Fetches meanings of the wordset's words in chunks, every request takes a permit of `limit`.
+/// Meanings keep the order of the wordset's words.
+async fn fetch_wordset_meanings(
+    client: &Client,
+    ws_id: i32,
+    limit: &Semaphore,
+) -> Result<Vec<Meaning>> {
+    log::info!("start fetching words of wordset {ws_id}");
+    let words = fetch_until_completed(|ps, p| async move {
+        let _permit = acquire(limit).await;
+        client.words_of_wordset(ws_id, ps, p).await
+    })
+    .await?;
     log::info!("got {} words", words.len());
 
     log::info!("start fetching meanings");
-    let mut meanings = Vec::with_capacity(words.len());
-    for chunk in words.chunks(50) {
-        meanings.extend(client
-            .meanings(
-                &(chunk
-                    .iter()
-                    .map(|w| w.meaning_id.to_string())
-                    .collect::<Vec<String>>()),
-            )
-            .await?);
-    }
+    // every chunk waits for a permit of its own, the permits bound the requests
+    let chunks: Vec<Vec<Meaning>> = try_join_all(words.chunks(MEANINGS_CHUNK).map(|chunk| {
+        let ids: Vec<String> = chunk.iter().map(|w| w.meaning_id.to_string()).collect();
+        async move {
+            let _permit = acquire(limit).await;
+            client.meanings(&ids).await
+        }
+    }))
+    .await?;
+    let meanings: Vec<Meaning> = chunks.into_iter().flatten().collect();
     log::info!("got {} meanings", meanings.len());
+    Ok(meanings)
+}
 
-    let filtered: HashSet<i32> = HashSet::from_iter(
-        db::filter_unexisted_word_ids(&meanings.iter().map(|m| m.id).collect()).await?,
-    );
-    log::info!("got {} new meanings", filtered.len());
+async fn save_wordset_meanings(db: &Store, ws_id: i32, meanings: Vec<Meaning>) -> Result<()> {
+    let meaning_ids: Vec<i32> = meanings.iter().map(|m| m.id).collect();
+    let stored: HashSet<i32> = db
+        .get_words_by_ids(&meaning_ids)
+        .await?
+        .into_keys()
+        .collect();
+    let (existing, new): (Vec<Meaning>, Vec<Meaning>) =
+        meanings.into_iter().partition(|m| stored.contains(&m.id));
+    log::info!("got {} new meanings", new.len());
 
     log::info!("start saving meanings to db");
-    let meanings: Vec<Meaning> = meanings
-        .into_iter()
-        .filter(|p| filtered.contains(&p.id))
-        .collect();
-    if meanings.len() > 0 {
-        db::save_new_ws_words(meanings, ws_id).await?;
+    if !new.is_empty() {
+        db.save_new_words(new).await?;
     }
+    let changed = db.update_changed_words(existing).await?;
+    log::info!("updated {} changed meanings", changed.len());
+    db.sync_ws_membership(ws_id, &meaning_ids).await?;
     Ok(())
 }
 
+async fn acquire(limit: &Semaphore) -> SemaphorePermit<'_> {
+    limit
+        .acquire()
+        .await
+        .expect("the semaphore is never closed")
+}
+
 async fn get_wordsets(client: &Client) -> Result<Vec<Wordset>> {
     let mut wordsets = fetch_until_completed(|ps, p| client.wordsets_page(ps, p)).await?;
     wordsets.push(client.default_wordset().await?.into());
     Ok(wordsets)
 }
 
-async fn fetch_until_completed<T, F, R, Fut>(call: F) -> Result<Vec<R>>
+async fn fetch_until_completed<T, F, R, Fut>(call: F) -> client::Result<Vec<R>>
 where
     T: Resp<R>,
     F: Fn(i32, i32) -> Fut,
-    Fut: Future<Output = Result<T>>,
+    Fut: Future<Output = client::Result<T>>,
 {
     let mut resp = call(100, 1).await?;
     let meta = resp.get_meta();
@@ -83,7 +231,14 @@ where
     while total as usize > result.len() {
         resp = call(100, current_page + 1).await?;
         current_page = resp.get_meta().current_page;
-        result.extend(resp.get_data());
+        let data = resp.get_data();
+        // asking again would bring the same empty page forever
+        if data.is_empty() {
+            return Err(client::Error::InvalidSkyengData(
+                "page is empty before the total is reached",
+            ));
+        }
+        result.extend(data);
     }
 
     Ok(result)
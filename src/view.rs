@@ -0,0 +1,386 @@
+use chrono::NaiveDateTime;
+use entity::{exports, media};
+use serde::Serialize;
+use serde_json::{json, Value};
+use skyeng_words::db::WordsetSummary;
+use skyeng_words::export::Entry;
+use skyeng_words::stats::{Stats, GOLD_3000};
+use std::str::FromStr;
+
+/// How `wordsets`, `words` and `stats` print what they've found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// Aligned columns with a header
+    Table,
+    Json,
+    /// Tab separated values without a header
+    Plain,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            "plain" => Ok(Output::Plain),
+            _ => Err(format!(
+                "unknown output {s}, expected one of: table, json, plain"
+            )),
+        }
+    }
+}
+
+pub fn wordsets(summaries: &[WordsetSummary], output: Output) {
+    if output == Output::Json {
+        let wordsets: Vec<Value> = summaries
+            .iter()
+            .map(|summary| {
+                json!({
+                    "id": summary.wordset.id,
+                    "name": summary.wordset.name,
+                    "words": summary.words,
+                    "unexported": summary.unexported,
+                    "synced_at": summary.wordset.synced_at,
+                    "removed_at": summary.wordset.removed_at,
+                })
+            })
+            .collect();
+        return print_json(&Value::Array(wordsets));
+    }
+    let rows = summaries
+        .iter()
+        .map(|summary| {
+            let mut name = summary.wordset.name.clone();
+            if summary.wordset.removed_at.is_some() {
+                name.push_str(" (removed)");
+            }
+            vec![
+                summary.wordset.id.to_string(),
+                name,
+                summary.words.to_string(),
+                summary.unexported.unwrap_or_default().to_string(),
+                summary.wordset.synced_at.map(date).unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_rows(
+        &["ID", "NAME", "WORDS", "UNEXPORTED", "SYNCED"],
+        rows,
+        output,
+    );
+}
+
+pub fn words(entries: &[Entry], output: Output) {
+    if output == Output::Json {
+        return print_json(&Value::Array(entries.iter().map(entry_json).collect()));
+    }
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.word.id.to_string(),
+                entry.word.text.clone(),
+                entry.word.translation.clone(),
+                entry.word.difficulty_level.to_string(),
+                wordset_names(entry),
+            ]
+        })
+        .collect();
+    print_rows(
+        &["ID", "TEXT", "TRANSLATION", "DIFFICULTY", "WORDSETS"],
+        rows,
+        output,
+    );
+}
+
+/// Every stored field of the word, fields with several values take a line per value
+pub fn word(entry: &Entry, exports: &[(exports::Model, i32)], output: Output) {
+    if output == Output::Json {
+        let mut word = entry_json(entry);
+        word["exports"] = exports
+            .iter()
+            .map(|(export, revision)| {
+                json!({
+                    "id": export.id,
+                    "target": export.target,
+                    "format": export.format,
+                    "created_at": export.created_at,
+                    "revision": revision,
+                    "reset": export.reset_at.is_some(),
+                })
+            })
+            .collect();
+        return print_json(&word);
+    }
+
+    let word = &entry.word;
+    let optional = |value: &Option<String>| value.iter().cloned().collect::<Vec<_>>();
+    let fields: Vec<(&str, Vec<String>)> = vec![
+        ("id", vec![word.id.to_string()]),
+        ("word_id", vec![word.word_id.to_string()]),
+        ("text", vec![word.text.clone()]),
+        ("translation", vec![word.translation.clone()]),
+        ("translation_note", optional(&word.translation_note)),
+        ("definition", vec![word.definition.clone()]),
+        ("transcription", optional(&word.transcription)),
+        (
+            "part_of_speech",
+            entry
+                .part_of_speech()
+                .map(str::to_string)
+                .into_iter()
+                .collect(),
+        ),
+        ("difficulty_level", vec![word.difficulty_level.to_string()]),
+        ("is_gold_3000", vec![word.is_gold_3000.to_string()]),
+        ("examples", entry.examples.clone()),
+        ("alternatives", entry.alternatives().collect()),
+        (
+            "wordsets",
+            entry
+                .wordsets
+                .iter()
+                .map(|ws| format!("{} {}", ws.id, ws.name))
+                .collect(),
+        ),
+        ("sound_url", optional(&word.sound_url)),
+        ("sound_file", entry.sound.iter().map(media_file).collect()),
+        ("image_url", optional(&word.image_url)),
+        ("image_file", entry.image.iter().map(media_file).collect()),
+        ("created_at", vec![date(word.created_at)]),
+        ("updated_at", vec![date(word.updated_at)]),
+        ("revision", vec![word.revision.to_string()]),
+        (
+            "removed_at",
+            word.removed_at.map(date).into_iter().collect(),
+        ),
+        (
+            "exports",
+            exports
+                .iter()
+                .map(|(export, revision)| {
+                    let reset = if export.reset_at.is_some() {
+                        " (reset)"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "{} {} to {} as {}, revision {revision}{reset}",
+                        export.id,
+                        date(export.created_at),
+                        export.target,
+                        export.format
+                    )
+                })
+                .collect(),
+        ),
+    ];
+    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, values) in fields {
+        for (i, value) in values.iter().enumerate() {
+            let value = one_line(value);
+            match output {
+                Output::Plain => println!("{name}\t{value}"),
+                _ if i == 0 => println!("{name:<width$}  {value}"),
+                _ => println!("{:<width$}  {value}", ""),
+            }
+        }
+    }
+}
+
+/// Name, header and rows of a part of the stats report
+type Section<'a> = (&'a str, Vec<&'a str>, Vec<Vec<String>>);
+
+/// Sections of the report one after another, plain output starts rows with their section
+pub fn stats(stats: &Stats, output: Output) {
+    if output == Output::Json {
+        return print_json(stats);
+    }
+    let sections: Vec<Section> = vec![
+        (
+            "total",
+            vec!["WORDS", "GOLD 3000", "COVERAGE"],
+            vec![vec![
+                stats.words.to_string(),
+                format!("{} of {GOLD_3000}", stats.gold_3000.words),
+                format!("{:.1}%", stats.gold_3000.coverage * 100.0),
+            ]],
+        ),
+        (
+            "wordset",
+            vec!["ID", "WORDSET", "WORDS"],
+            stats
+                .wordsets
+                .iter()
+                .map(|ws| vec![ws.id.to_string(), ws.name.clone(), ws.words.to_string()])
+                .collect(),
+        ),
+        (
+            "difficulty",
+            vec!["DIFFICULTY", "WORDS"],
+            stats
+                .difficulty
+                .iter()
+                .map(|level| vec![level.level.to_string(), level.words.to_string()])
+                .collect(),
+        ),
+        (
+            "week",
+            vec!["WEEK", "ADDED", "TOTAL"],
+            stats
+                .weeks
+                .iter()
+                .map(|week| {
+                    vec![
+                        week.start.clone(),
+                        week.added.to_string(),
+                        week.total.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+        (
+            "target",
+            vec!["TARGET", "EXPORTED", "PENDING"],
+            stats
+                .targets
+                .iter()
+                .map(|target| {
+                    vec![
+                        target.target.clone(),
+                        target.exported.to_string(),
+                        target.pending.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+    ];
+    for (i, (section, header, rows)) in sections.into_iter().enumerate() {
+        match output {
+            Output::Plain => {
+                let rows = rows
+                    .into_iter()
+                    .map(|row| std::iter::once(section.to_string()).chain(row).collect())
+                    .collect();
+                print_rows(&header, rows, output);
+            }
+            _ => {
+                if i > 0 {
+                    println!();
+                }
+                print_rows(&header, rows, output);
+            }
+        }
+    }
+}
+
+fn entry_json(entry: &Entry) -> Value {
+    let word = &entry.word;
+    json!({
+        "id": word.id,
+        "word_id": word.word_id,
+        "text": word.text,
+        "translation": word.translation,
+        "translation_note": word.translation_note,
+        "definition": word.definition,
+        "transcription": word.transcription,
+        "part_of_speech": entry.part_of_speech(),
+        "difficulty_level": word.difficulty_level,
+        "is_gold_3000": word.is_gold_3000,
+        "examples": entry.examples,
+        "alternatives": entry
+            .alternatives
+            .iter()
+            .map(|alt| json!({ "text": alt.text, "translation": alt.translation }))
+            .collect::<Vec<_>>(),
+        "wordsets": entry
+            .wordsets
+            .iter()
+            .map(|ws| json!({ "id": ws.id, "name": ws.name }))
+            .collect::<Vec<_>>(),
+        "sound_url": word.sound_url,
+        "sound_file": entry.sound.as_ref().map(media_json),
+        "image_url": word.image_url,
+        "image_file": entry.image.as_ref().map(media_json),
+        "created_at": word.created_at,
+        "updated_at": word.updated_at,
+        "revision": word.revision,
+        "removed_at": word.removed_at,
+    })
+}
+
+fn media_json(media: &media::Model) -> Value {
+    json!({
+        "path": media.path,
+        "size": media.size,
+        "checksum": media.checksum,
+        "content_type": media.content_type,
+    })
+}
+
+/// Where the file is relative to the media directory
+fn media_file(media: &media::Model) -> String {
+    format!("{} ({} bytes)", media.path, media.size)
+}
+
+fn wordset_names(entry: &Entry) -> String {
+    entry
+        .wordsets
+        .iter()
+        .map(|ws| ws.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_json(value: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("values serialize")
+    );
+}
+
+/// Pads every column but the last one to its widest value, plain output only separates them
+fn print_rows(header: &[&str], rows: Vec<Vec<String>>, output: Output) {
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|row| row.iter().map(|value| one_line(value)).collect())
+        .collect();
+    if output == Output::Plain {
+        for row in rows {
+            println!("{}", row.join("\t"));
+        }
+        return;
+    }
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|name| name.to_string()).collect();
+    for row in std::iter::once(&header).chain(&rows) {
+        let last = row.len() - 1;
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, value)| match i {
+                _ if i == last => value.clone(),
+                _ => format!("{value:<width$}", width = widths[i]),
+            })
+            .collect();
+        println!("{}", line.join("  "));
+    }
+}
+
+/// Tabs and line breaks would break rows apart
+fn one_line(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn date(timestamp: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::*;
+use rusqlite::Connection;
+use serde_json::Value;
+use skyeng_words::db::Store;
+use skyeng_words::export::{Anki, ExportOptions, Exporter};
+use skyeng_words::sync;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Read};
+use wiremock::MockServer;
+
+/// A note of the exported collection, `deck` is the name of its card's deck
+#[derive(Debug, PartialEq)]
+struct Note {
+    id: i64,
+    card: i64,
+    guid: String,
+    fields: Vec<String>,
+    deck: String,
+}
+
+/// Unpacks the package and reads its collection back: the note type and the notes by guid
+fn open_package(package: Vec<u8>) -> (Value, BTreeMap<String, Note>) {
+    let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+    let mut collection = Vec::new();
+    archive
+        .by_name("collection.anki2")
+        .unwrap()
+        .read_to_end(&mut collection)
+        .unwrap();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), collection).unwrap();
+    let conn = Connection::open(file.path()).unwrap();
+
+    let (models, decks): (String, String) = conn
+        .query_row("SELECT models, decks FROM col", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    let models: BTreeMap<String, Value> = serde_json::from_str(&models).unwrap();
+    assert_eq!(models.len(), 1);
+    let model = models.into_values().next().unwrap();
+    let decks: BTreeMap<String, Value> = serde_json::from_str(&decks).unwrap();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT notes.id, notes.guid, notes.flds, notes.mid, cards.did, cards.id
+             FROM notes JOIN cards ON cards.nid = notes.id",
+        )
+        .unwrap();
+    let notes = stmt
+        .query_map([], |row| {
+            let model_id: i64 = row.get(3)?;
+            assert_eq!(model_id, model["id"].as_i64().unwrap());
+            let deck: i64 = row.get(4)?;
+            let flds: String = row.get(2)?;
+            Ok(Note {
+                id: row.get(0)?,
+                card: row.get(5)?,
+                guid: row.get(1)?,
+                fields: flds.split('\x1f').map(str::to_string).collect(),
+                deck: decks[&deck.to_string()]["name"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            })
+        })
+        .unwrap()
+        .map(|note| note.unwrap())
+        .map(|note| (note.guid.clone(), note))
+        .collect();
+    (model, notes)
+}
+
+async fn export(db: &Store) -> (Value, BTreeMap<String, Note>) {
+    let entries = db.get_all_words(false).await.unwrap();
+    let mut package = Vec::new();
+    Anki.export(&entries, &mut package, &ExportOptions::default())
+        .unwrap();
+    open_package(package)
+}
+
+async fn mount_words(server: &MockServer, ticket_translation: &str) {
+    mount_wordsets(server, &[(1, "Travel"), (2, "Food")], (3, "My words")).await;
+    mount_wordset_words(server, 1, &[1, 2]).await;
+    mount_wordset_words(server, 2, &[2, 3]).await;
+    mount_wordset_words(server, 3, &[]).await;
+    mount_meanings(
+        server,
+        vec![
+            meaning(
+                1,
+                "luggage",
+                "багаж",
+                &["Pack your luggage.", "Bags & <boxes>"],
+            ),
+            meaning(2, "ticket", ticket_translation, &[]),
+            meaning(3, "soup", "суп", &["Hot soup."]),
+        ],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn package_holds_a_note_per_word_in_wordset_decks() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_words(&server, "билет").await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    let (model, notes) = export(&db).await;
+    assert_eq!(model["name"], "Skyeng word");
+    let fields: Vec<&str> = model["flds"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            "Text",
+            "Translation",
+            "Definition",
+            "Examples",
+            "Transcription",
+            "PartOfSpeech",
+            "Audio",
+            "Image"
+        ]
+    );
+
+    let decks: Vec<(&str, &str)> = notes
+        .values()
+        .map(|note| (note.guid.as_str(), note.deck.as_str()))
+        .collect();
+    // a word of several wordsets gets a note in each of their decks
+    assert_eq!(
+        decks,
+        [
+            ("skyeng-meaning-1-wordset-1", "Skyeng::Travel"),
+            ("skyeng-meaning-2-wordset-1", "Skyeng::Travel"),
+            ("skyeng-meaning-2-wordset-2", "Skyeng::Food"),
+            ("skyeng-meaning-3-wordset-2", "Skyeng::Food")
+        ]
+    );
+    assert_eq!(
+        notes["skyeng-meaning-2-wordset-1"].fields,
+        notes["skyeng-meaning-2-wordset-2"].fields
+    );
+    // cards and notes don't share ids
+    let note_ids: BTreeSet<i64> = notes.values().map(|note| note.id).collect();
+    assert_eq!(note_ids.len(), notes.len());
+    assert!(notes.values().all(|note| !note_ids.contains(&note.card)));
+    assert_eq!(
+        notes["skyeng-meaning-1-wordset-1"].fields,
+        [
+            "luggage",
+            "багаж",
+            "definition of luggage",
+            "Pack your luggage.<br>Bags &amp; &lt;boxes&gt;",
+            "luggage-ipa",
+            "noun",
+            "",
+            ""
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reexported_notes_keep_their_ids() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_words(&server, "билет").await;
+    sync::sync(&client, &db, 4).await.unwrap();
+    let (_, before) = export(&db).await;
+
+    server.reset().await;
+    mount_login(&server).await;
+    mount_words(&server, "проездной билет").await;
+    sync::sync(&client, &db, 4).await.unwrap();
+    let (_, after) = export(&db).await;
+
+    // Anki matches notes by guid, the same word updates the note it was imported as
+    let ids = |notes: &BTreeMap<String, Note>| -> Vec<(String, i64, i64)> {
+        notes
+            .values()
+            .map(|note| (note.guid.clone(), note.id, note.card))
+            .collect()
+    };
+    assert_eq!(ids(&before), ids(&after));
+    assert_eq!(before["skyeng-meaning-2-wordset-2"].fields[1], "билет");
+    assert_eq!(
+        after["skyeng-meaning-2-wordset-2"].fields[1],
+        "проездной билет"
+    );
+    assert_eq!(
+        before["skyeng-meaning-1-wordset-1"],
+        after["skyeng-meaning-1-wordset-1"]
+    );
+}
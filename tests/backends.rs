@@ -0,0 +1,145 @@
+use entity::{examples, export_items, exports, words, wordset_words};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, ModelTrait,
+    QueryOrder, Statement, TransactionTrait,
+};
+use skyeng_words::db::Store;
+use skyeng_words::search::SearchOptions;
+use std::time::Duration;
+
+/// Migrations creating the legacy layout: words with joined examples, a wordset id
+/// and the exported flag
+const LEGACY_MIGRATIONS: u32 = 3;
+
+async fn exec(conn: &DatabaseConnection, sql: &str) {
+    conn.execute(Statement::from_string(
+        conn.get_database_backend(),
+        sql.to_owned(),
+    ))
+    .await
+    .unwrap();
+}
+
+/// Migrates a legacy row all the way up, checks the entities against the resulting schema,
+/// then rolls everything back and migrates again.
+///
+/// `url` must point to an empty database.
+async fn check_backend(url: &str) {
+    let conn = Database::connect(url).await.unwrap();
+    Migrator::up(&conn, Some(LEGACY_MIGRATIONS)).await.unwrap();
+    exec(
+        &conn,
+        "INSERT INTO words (id, word_id, difficulty_level, text, translation, definition, \
+        is_gold_3000, examples, wordset_id, exported) \
+        VALUES (1, 10, 2, 'luggage', 'багаж', '', true, 'Pack it, please,Lost it', 7, true)",
+    )
+    .await;
+    Migrator::up(&conn, None).await.unwrap();
+
+    let word = words::Entity::find_by_id(1)
+        .one(&conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(word.text, "luggage");
+    assert!(word.is_gold_3000);
+    assert_eq!(word.revision, 1);
+    let examples: Vec<String> = word
+        .find_related(examples::Entity)
+        .order_by_asc(examples::Column::Position)
+        .all(&conn)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.text)
+        .collect();
+    assert_eq!(examples, ["Pack it, please", "Lost it"]);
+    // words stored before the search index are found too
+    let hits = Store::new(conn.clone())
+        .search("pack", &SearchOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(hits[0].entry.word.text, "luggage");
+    let memberships = wordset_words::Entity::find().all(&conn).await.unwrap();
+    assert_eq!(memberships[0].wordset_id, 7);
+
+    // the exported flag became a batch of the xlsx target
+    let batches = exports::Entity::find().all(&conn).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(
+        (batches[0].target.as_str(), batches[0].word_count),
+        ("xlsx", 1)
+    );
+    let items = export_items::Entity::find().all(&conn).await.unwrap();
+    assert_eq!((items[0].meaning_id, items[0].revision), (1, 1));
+
+    // ids of new batches are generated by the database
+    let batch = exports::ActiveModel {
+        id: NotSet,
+        created_at: Set(4_102_444_800),
+        format: Set("csv".into()),
+        destination: Set("-".into()),
+        word_count: Set(0),
+        target: Set("csv".into()),
+        reset_at: Set(None),
+        options: Set(Some("{}".into())),
+    }
+    .insert(&conn)
+    .await
+    .unwrap();
+    assert!(batch.id > batches[0].id);
+    assert_eq!(batch.created_at, 4_102_444_800);
+    assert!(batches[0].options.is_none());
+
+    Migrator::down(&conn, None).await.unwrap();
+    Migrator::up(&conn, None).await.unwrap();
+    Migrator::down(&conn, None).await.unwrap();
+}
+
+/// Checks that a store of a server database isn't limited to a single connection,
+/// leaves the database empty
+async fn check_pool(url: &str) {
+    let db = Store::connect(url).await.unwrap();
+    let txn = db.connection().begin().await.unwrap();
+    // with a single connection this would wait for the transaction forever
+    let exports = tokio::time::timeout(Duration::from_secs(5), db.get_exports(None))
+        .await
+        .expect("a second connection is available");
+    assert!(exports.unwrap().is_empty());
+    txn.rollback().await.unwrap();
+    Migrator::down(db.connection(), None).await.unwrap();
+}
+
+#[tokio::test]
+async fn migrations_on_sqlite() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!(
+        "sqlite://{}?mode=rwc",
+        dir.path().join("words.db").display()
+    );
+    check_backend(&url).await;
+}
+
+#[tokio::test]
+async fn migrations_on_postgres() {
+    match std::env::var("SKYENG_TEST_POSTGRES_URL") {
+        Ok(url) => {
+            check_backend(&url).await;
+            check_pool(&url).await;
+        }
+        Err(_) => eprintln!("SKYENG_TEST_POSTGRES_URL is not set, skipping"),
+    }
+}
+
+#[tokio::test]
+async fn migrations_on_mysql() {
+    match std::env::var("SKYENG_TEST_MYSQL_URL") {
+        Ok(url) => {
+            check_backend(&url).await;
+            check_pool(&url).await;
+        }
+        Err(_) => eprintln!("SKYENG_TEST_MYSQL_URL is not set, skipping"),
+    }
+}
@@ -0,0 +1,321 @@
+mod common;
+
+use common::*;
+use serde_json::json;
+use skyeng_words::client::{Client, ClientConfig, Error, Resp};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn login_posts_credentials_with_hidden_form_fields() {
+    let server = MockServer::start().await;
+    logged_in_client(&server).await;
+
+    let submit = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|r| r.url.path() == "/frame/login-submit")
+        .unwrap();
+    let body = String::from_utf8(submit.body).unwrap();
+    for field in ["username=user", "password=secret", "csrfToken=csrf-token"] {
+        assert!(body.split('&').any(|f| f == field), "{field} not in {body}");
+    }
+}
+
+#[tokio::test]
+async fn login_fails_on_rejected_credentials() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/frame/login-submit"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": false,
+            "message": "wrong password",
+            "code": "invalid_credentials",
+        })))
+        .mount(&server)
+        .await;
+    mount_login(&server).await;
+
+    let client = Client::with_config("user".into(), "wrong".into(), config(&server)).unwrap();
+    let err = client.login().await.unwrap_err();
+    assert!(
+        matches!(&err, Error::BadCredentials { message, .. } if message == "wrong password"),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn login_reports_captcha() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/frame/login-submit"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": false,
+            "message": "Подтвердите, что вы не робот",
+            "code": "captcha_required",
+        })))
+        .mount(&server)
+        .await;
+    mount_login(&server).await;
+
+    let client = Client::with_config("user".into(), "secret".into(), config(&server)).unwrap();
+    let err = client.login().await.unwrap_err();
+    assert!(matches!(err, Error::CaptchaRequired), "{err:?}");
+}
+
+#[tokio::test]
+async fn unexpected_payload_is_kept() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"error": "gone"}"#))
+        .mount(&server)
+        .await;
+
+    let err = client.meanings(&["1".to_string()]).await.unwrap_err();
+    assert!(
+        matches!(&err, Error::Schema { payload, .. } if payload == r#"{"error": "gone"}"#),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn wordsets_page_is_requested_with_token_and_student_id() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-vimbox/v1/wordsets.json"))
+        .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+        .and(query_param("studentId", USER_ID.to_string()))
+        .and(query_param("pageSize", "10"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page(
+            vec![json!({ "id": 7, "title": "Travel" })],
+            11,
+            2,
+            10,
+        )))
+        .mount(&server)
+        .await;
+
+    let resp = client.wordsets_page(10, 2).await.unwrap();
+    assert_eq!(resp.get_meta().total, 11);
+    assert_eq!(resp.get_meta().current_page, 2);
+    let wordsets = resp.get_data();
+    assert_eq!(wordsets.len(), 1);
+    assert_eq!(wordsets[0].title, "Travel");
+}
+
+#[tokio::test]
+async fn words_of_wordset_are_requested_per_wordset() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/wordsets/7/words.json"))
+        .and(query_param("studentId", USER_ID.to_string()))
+        .and(query_param("wordsetId", "7"))
+        .and(query_param("page", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page(
+            vec![json!({ "meaningId": 1 }), json!({ "meaningId": 2 })],
+            2,
+            1,
+            100,
+        )))
+        .mount(&server)
+        .await;
+
+    let resp = client.words_of_wordset(7, 100, 1).await.unwrap();
+    let ids: Vec<i32> = resp.get_data().iter().map(|w| w.meaning_id).collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn meanings_are_fetched_by_joined_ids() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_meanings(
+        &server,
+        vec![
+            meaning(1, "luggage", "багаж", &["Pack your luggage, please."]),
+            meaning(2, "ticket", "билет", &[]),
+            meaning(3, "train", "поезд", &[]),
+        ],
+    )
+    .await;
+
+    let meanings = client
+        .meanings(&["1".to_string(), "3".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(meanings.len(), 2);
+    assert_eq!(meanings[0].text, "luggage");
+    assert_eq!(meanings[0].translation.text, "багаж");
+    assert_eq!(meanings[0].examples[0].text, "Pack your luggage, please.");
+    assert_eq!(meanings[1].text, "train");
+}
+
+#[tokio::test]
+async fn throttled_and_failed_requests_are_retried() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mount_meanings(&server, vec![meaning(1, "luggage", "багаж", &[])]).await;
+
+    let meanings = client.meanings(&["1".to_string()]).await.unwrap();
+    assert_eq!(meanings.len(), 1);
+    let attempts = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|r| r.url.path() == "/api/for-services/v2/meanings")
+        .count();
+    assert_eq!(attempts, 3);
+}
+
+#[tokio::test]
+async fn credentials_are_submitted_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/frame/login-submit"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_login(&server).await;
+
+    let client = Client::with_config("user".into(), "secret".into(), config(&server)).unwrap();
+    let err = client.login().await.unwrap_err();
+    assert!(
+        matches!(err, Error::Status { status, .. } if status == 503),
+        "{err:?}"
+    );
+}
+
+fn login_submits(requests: &[wiremock::Request]) -> usize {
+    requests
+        .iter()
+        .filter(|r| r.url.path() == "/frame/login-submit")
+        .count()
+}
+
+#[tokio::test]
+async fn cached_session_is_reused() {
+    let server = MockServer::start().await;
+    mount_login(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = ClientConfig {
+        session_file: Some(dir.path().join("session.json")),
+        ..config(&server)
+    };
+
+    for _ in 0..2 {
+        let client = Client::with_config("user".into(), "secret".into(), config.clone()).unwrap();
+        client.login().await.unwrap();
+    }
+    assert_eq!(login_submits(&server.received_requests().await.unwrap()), 1);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let meta = std::fs::metadata(dir.path().join("session.json")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+    }
+
+    // another account doesn't get someone else's session
+    let client = Client::with_config("other".into(), "secret".into(), config).unwrap();
+    client.login().await.unwrap();
+    assert_eq!(login_submits(&server.received_requests().await.unwrap()), 2);
+}
+
+#[tokio::test]
+async fn expired_session_is_renewed_on_unauthorized() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(ResponseTemplate::new(401))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    mount_meanings(&server, vec![meaning(1, "luggage", "багаж", &[])]).await;
+
+    let meanings = client.meanings(&["1".to_string()]).await.unwrap();
+    assert_eq!(meanings.len(), 1);
+    assert_eq!(login_submits(&server.received_requests().await.unwrap()), 2);
+}
+
+#[tokio::test]
+async fn session_rejected_after_logging_in_again_has_expired() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let err = client.meanings(&["1".to_string()]).await.unwrap_err();
+    assert!(matches!(err, Error::SessionExpired), "{err:?}");
+    assert_eq!(login_submits(&server.received_requests().await.unwrap()), 2);
+}
+
+#[tokio::test]
+async fn unauthorized_downloads_do_not_log_in_again() {
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/sounds/1.mp3"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/sounds/1.mp3", server.uri());
+    let err = client.download(&url).await.unwrap_err();
+    assert!(
+        matches!(err, Error::Status { status, .. } if status == 401),
+        "{err:?}"
+    );
+    assert_eq!(login_submits(&server.received_requests().await.unwrap()), 1);
+}
+
+#[tokio::test]
+async fn api_calls_before_login_fail() {
+    let server = MockServer::start().await;
+    let client = Client::with_config("user".into(), "secret".into(), config(&server)).unwrap();
+    let err = client.meanings(&["1".to_string()]).await.unwrap_err();
+    assert!(matches!(err, Error::NotLoggedIn), "{err:?}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rejected_fresh_token_is_not_an_expired_session() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/users"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+    mount_login(&server).await;
+
+    let client = Client::with_config("user".into(), "secret".into(), config(&server)).unwrap();
+    let err = client.login().await.unwrap_err();
+    assert!(
+        matches!(err, Error::Status { status, .. } if status == 401),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,212 @@
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use skyeng_words::client::{Client, ClientConfig, RetryPolicy};
+use skyeng_words::db::Store;
+use skyeng_words::sync;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// JWT expiring in 2100
+pub const TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJleHAiOjQxMDI0NDQ4MDB9.signature";
+pub const USER_ID: i32 = 42;
+
+const LOGIN_PAGE: &str = r#"<html><body>
+<form class="authentication-page__form" action="/frame/login-submit" method="post">
+    <input type="hidden" name="csrfToken" value="csrf-token">
+    <input type="text" name="username">
+    <input type="password" name="password">
+</form>
+</body></html>"#;
+
+pub fn config(server: &MockServer) -> ClientConfig {
+    ClientConfig {
+        id_url: server.uri(),
+        student_api_url: server.uri(),
+        words_api_url: server.uri(),
+        dictionary_url: server.uri(),
+        retry: RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        },
+        rate_limit: None,
+        session_file: None,
+    }
+}
+
+/// Serves the whole login flow: form page, submit, redirect, jwt cookie and users list
+pub async fn mount_login(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(LOGIN_PAGE))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/frame/login-submit"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "redirect": format!("{}/after-login", server.uri()),
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/after-login"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/user-api/v1/auth/jwt"))
+        .respond_with(ResponseTemplate::new(200).insert_header(
+            "set-cookie",
+            format!("token_global={TOKEN}; path=/").as_str(),
+        ))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v2/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "users": [{ "id": USER_ID }],
+        })))
+        .mount(server)
+        .await;
+}
+
+pub async fn logged_in_client(server: &MockServer) -> Client {
+    mount_login(server).await;
+    let client = Client::with_config("user".into(), "secret".into(), config(server)).unwrap();
+    client.login().await.unwrap();
+    client
+}
+
+pub fn page(data: Vec<Value>, total: usize, current_page: i32, page_size: i32) -> Value {
+    json!({
+        "meta": {
+            "total": total,
+            "currentPage": current_page,
+            "lastPage": (total as i32 + page_size - 1) / page_size,
+            "pageSize": page_size,
+        },
+        "data": data,
+    })
+}
+
+pub fn meaning(id: i32, text: &str, translation: &str, examples: &[&str]) -> Value {
+    json!({
+        "id": id,
+        "wordId": id * 10,
+        "difficultyLevel": 2,
+        "text": text,
+        "translation": { "text": translation },
+        "definition": { "text": format!("definition of {text}") },
+        "isGold3000": id % 2 == 0,
+        "examples": examples.iter().map(|e| json!({ "text": e })).collect::<Vec<Value>>(),
+        "transcription": format!("{text}-ipa"),
+        "partOfSpeechCode": "n",
+        "soundUrl": format!("//cdn.example/sounds/{id}.mp3"),
+        "images": [{ "url": format!("//cdn.example/images/{id}.jpg") }],
+        "alternativeTranslations": [
+            { "text": format!("{text}s"), "translation": { "text": format!("{translation} (мн.)") } },
+        ],
+    })
+}
+
+pub async fn mount_wordsets(server: &MockServer, wordsets: &[(i32, &str)], default: (i32, &str)) {
+    Mock::given(method("GET"))
+        .and(path("/api/for-vimbox/v1/wordsets.json"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(page(
+                wordsets
+                    .iter()
+                    .map(|(id, title)| json!({ "id": id, "title": title }))
+                    .collect(),
+                wordsets.len(),
+                1,
+                100,
+            )),
+        )
+        .mount(server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/api/for-mobile/v1/wordsets/default.json"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "id": default.0, "title": default.1 })),
+        )
+        .mount(server)
+        .await;
+}
+
+pub async fn mount_wordset_words(server: &MockServer, wordset_id: i32, meaning_ids: &[i32]) {
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v1/wordsets/{wordset_id}/words.json")))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(page(
+                meaning_ids
+                    .iter()
+                    .map(|id| json!({ "meaningId": id }))
+                    .collect(),
+                meaning_ids.len(),
+                1,
+                100,
+            )),
+        )
+        .mount(server)
+        .await;
+}
+
+/// Answers every `meanings` call with the meanings whose ids were asked for
+pub async fn mount_meanings(server: &MockServer, meanings: Vec<Value>) {
+    mount_slow_meanings(server, meanings, Duration::ZERO).await
+}
+
+/// Like `mount_meanings`, every answer takes `delay`
+pub async fn mount_slow_meanings(server: &MockServer, meanings: Vec<Value>, delay: Duration) {
+    Mock::given(method("GET"))
+        .and(path("/api/for-services/v2/meanings"))
+        .respond_with(move |req: &wiremock::Request| {
+            let ids: Vec<i64> = req
+                .url
+                .query_pairs()
+                .find(|(k, _)| k == "ids")
+                .map(|(_, v)| v.split(',').filter_map(|id| id.parse().ok()).collect())
+                .unwrap_or_default();
+            let found: Vec<&Value> = meanings
+                .iter()
+                .filter(|m| ids.contains(&m["id"].as_i64().unwrap()))
+                .collect();
+            ResponseTemplate::new(200)
+                .set_body_json(found)
+                .set_delay(delay)
+        })
+        .mount(server)
+        .await;
+}
+
+/// Id, title and meaning ids of a wordset
+pub type Wordset<'a> = (i32, &'a str, &'a [i32]);
+
+/// Serves the wordsets with their words and `meanings`, the last wordset is the default one
+pub async fn mount_account(server: &MockServer, wordsets: &[Wordset<'_>], meanings: Vec<Value>) {
+    let (default, others) = wordsets.split_last().expect("there is a default wordset");
+    let titles: Vec<(i32, &str)> = others.iter().map(|(id, title, _)| (*id, *title)).collect();
+    mount_wordsets(server, &titles, (default.0, default.1)).await;
+    for (id, _, meaning_ids) in wordsets {
+        mount_wordset_words(server, *id, meaning_ids).await;
+    }
+    mount_meanings(server, meanings).await;
+}
+
+/// Syncs the account of `mount_account` into a new in-memory store
+pub async fn sync_account(
+    wordsets: &[Wordset<'_>],
+    meanings: Vec<Value>,
+) -> (Store, MockServer, Client) {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_account(&server, wordsets, meanings).await;
+    sync::sync(&client, &db, 4).await.unwrap();
+    (db, server, client)
+}
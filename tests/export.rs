@@ -0,0 +1,92 @@
+mod common;
+
+use common::*;
+use skyeng_words::db::{Store, WordFilter};
+use skyeng_words::export::{self, Delimited, Destination, ExportOptions, Job, Mode, Outcome};
+use std::fs;
+use std::path::Path;
+
+/// Luggage and ticket of Travel, ticket and soup of Food
+async fn synced() -> Store {
+    let wordsets = [
+        (1, "Travel", &[1, 2][..]),
+        (2, "Food", &[2, 3]),
+        (3, "My words", &[]),
+    ];
+    let meanings = vec![
+        meaning(1, "luggage", "багаж", &[]),
+        meaning(2, "ticket", "билет", &[]),
+        meaning(3, "soup", "суп", &[]),
+    ];
+    sync_account(&wordsets, meanings).await.0
+}
+
+async fn run(db: &Store, path: &Path, mode: Mode) -> Outcome {
+    let exporter = Delimited::csv();
+    let job = Job {
+        exporter: &exporter,
+        words: db.get_all_words(false).await.unwrap(),
+        target: "csv".to_string(),
+        destination: Destination::File(path.into()),
+        options: ExportOptions::default(),
+        overwrite: false,
+    };
+    export::run(db, job, mode).await.unwrap()
+}
+
+async fn unexported(db: &Store) -> usize {
+    let filter = WordFilter {
+        not_exported_to: Some("csv".to_string()),
+        ..WordFilter::default()
+    };
+    db.find_words(&filter).await.unwrap().len()
+}
+
+#[tokio::test]
+async fn dry_run_writes_and_records_nothing() {
+    let db = synced().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("words.csv");
+
+    let preview = match run(&db, &path, Mode::DryRun { rows: 2 }).await {
+        Outcome::Previewed(preview) => preview,
+        Outcome::Written(_) => panic!("a dry run writes nothing"),
+    };
+    assert_eq!(preview.words, 3);
+    assert_eq!(preview.per_wordset["Travel"], 2);
+    assert_eq!(preview.per_wordset["Food"], 2);
+    assert_eq!(preview.rows.len(), 2);
+    assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    assert!(db.get_exports(None).await.unwrap().is_empty());
+    assert_eq!(unexported(&db).await, 3);
+}
+
+#[tokio::test]
+async fn no_mark_writes_the_file_only() {
+    let db = synced().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("words.csv");
+
+    assert!(matches!(
+        run(&db, &path, Mode::NoMark).await,
+        Outcome::Written(None)
+    ));
+    assert!(fs::read_to_string(&path).unwrap().contains("luggage"));
+    assert!(db.get_exports(None).await.unwrap().is_empty());
+    assert_eq!(unexported(&db).await, 3);
+}
+
+#[tokio::test]
+async fn recorded_exports_make_a_batch() {
+    let db = synced().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("words.csv");
+
+    let batch = match run(&db, &path, Mode::Record).await {
+        Outcome::Written(Some(batch)) => batch,
+        _ => panic!("the export is recorded"),
+    };
+    assert_eq!((batch.target.as_str(), batch.word_count), ("csv", 3));
+    assert!(path.exists());
+    assert_eq!(unexported(&db).await, 0);
+}
@@ -0,0 +1,214 @@
+mod common;
+
+use common::*;
+use entity::words;
+use regex::Regex;
+use skyeng_words::db::{Store, WordFilter};
+use skyeng_words::export::{prepare_output, Destination, ExportOptions};
+
+async fn texts(db: &Store, filter: WordFilter) -> Vec<String> {
+    db.find_words(&filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.word.text)
+        .collect()
+}
+
+/// Luggage and ticket of Travel, soup and bread of Food with difficulty levels 1, 3, 4 and 5
+async fn synced() -> Store {
+    let mut meanings = vec![
+        meaning(1, "luggage", "багаж", &[]),
+        meaning(2, "ticket", "билет", &[]),
+        meaning(3, "soup", "суп", &[]),
+        meaning(4, "bread", "хлеб", &[]),
+    ];
+    for (meaning, difficulty) in meanings.iter_mut().zip([1, 3, 4, 5]) {
+        meaning["difficultyLevel"] = difficulty.into();
+    }
+    let wordsets = [
+        (1, "Travel", &[1, 2][..]),
+        (2, "Food", &[3, 4]),
+        (3, "My words", &[]),
+    ];
+    sync_account(&wordsets, meanings).await.0
+}
+
+fn not_exported_to(target: &str) -> WordFilter {
+    WordFilter {
+        not_exported_to: Some(target.to_string()),
+        ..WordFilter::default()
+    }
+}
+
+async fn all_words(db: &Store) -> Vec<words::Model> {
+    db.find_word_rows(&WordFilter::default()).await.unwrap()
+}
+
+#[tokio::test]
+async fn no_filter_keeps_every_word() {
+    let db = synced().await;
+    assert_eq!(
+        texts(&db, WordFilter::default()).await,
+        ["luggage", "ticket", "soup", "bread"]
+    );
+}
+
+#[tokio::test]
+async fn wordset_filter_keeps_words_of_the_wordsets() {
+    let db = synced().await;
+    let filter = WordFilter {
+        wordsets: vec![1],
+        ..WordFilter::default()
+    };
+    assert_eq!(texts(&db, filter).await, ["luggage", "ticket"]);
+}
+
+#[tokio::test]
+async fn difficulty_range_includes_its_bounds() {
+    let db = synced().await;
+    let filter = WordFilter {
+        min_difficulty: Some(3),
+        max_difficulty: Some(4),
+        ..WordFilter::default()
+    };
+    assert_eq!(texts(&db, filter).await, ["ticket", "soup"]);
+}
+
+#[tokio::test]
+async fn gold_only_combines_with_other_filters() {
+    let db = synced().await;
+    // the fixture makes even ids Gold 3000
+    let filter = WordFilter {
+        gold_only: true,
+        min_difficulty: Some(3),
+        ..WordFilter::default()
+    };
+    assert_eq!(texts(&db, filter).await, ["ticket", "bread"]);
+}
+
+#[tokio::test]
+async fn pattern_matches_text_or_translation() {
+    let db = synced().await;
+    let filter = WordFilter {
+        pattern: Some(Regex::new("^(lug|хл)").unwrap()),
+        ..WordFilter::default()
+    };
+    assert_eq!(texts(&db, filter).await, ["luggage", "bread"]);
+}
+
+#[tokio::test]
+async fn added_range_includes_since_and_excludes_until() {
+    let db = synced().await;
+    let added: Vec<i64> = all_words(&db).await.iter().map(|w| w.created_at).collect();
+    let (first, last) = (*added.iter().min().unwrap(), *added.iter().max().unwrap());
+
+    let after_last = WordFilter {
+        since: Some(last + 1),
+        ..WordFilter::default()
+    };
+    assert!(texts(&db, after_last).await.is_empty());
+    let food = WordFilter {
+        since: Some(first),
+        until: Some(last + 1),
+        wordsets: vec![2],
+        ..WordFilter::default()
+    };
+    assert_eq!(texts(&db, food).await, ["soup", "bread"]);
+}
+
+#[tokio::test]
+async fn exported_words_are_skipped_for_their_target_only() {
+    let db = synced().await;
+    let exported = db.get_words_by_ids(&[1, 3]).await.unwrap();
+    let exported: Vec<_> = [1, 3].iter().map(|id| exported[id].clone()).collect();
+    db.record_export("csv", "csv", "-", &exported)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        texts(&db, not_exported_to("csv")).await,
+        ["ticket", "bread"]
+    );
+    assert_eq!(
+        texts(&db, not_exported_to("xlsx")).await,
+        ["luggage", "ticket", "soup", "bread"]
+    );
+}
+
+#[tokio::test]
+async fn reset_target_brings_its_words_back() {
+    let db = synced().await;
+    db.record_export("csv", "csv", "-", &all_words(&db).await)
+        .await
+        .unwrap();
+    db.record_export("xlsx", "xlsx", "words.xlsx", &all_words(&db).await)
+        .await
+        .unwrap();
+
+    assert_eq!(db.reset_target("csv").await.unwrap(), 1);
+    assert_eq!(texts(&db, not_exported_to("csv")).await.len(), 4);
+    assert!(texts(&db, not_exported_to("xlsx")).await.is_empty());
+    // reset batches stay in the history
+    assert_eq!(db.get_exports(Some("csv")).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn failed_publish_rolls_the_batch_back() {
+    let db = synced().await;
+    let failed = db
+        .record_export_with(
+            "csv",
+            "csv",
+            "out.csv",
+            &ExportOptions::default(),
+            &all_words(&db).await,
+            || anyhow::bail!("disk full"),
+        )
+        .await;
+    assert!(failed.is_err());
+    assert!(db.get_exports(Some("csv")).await.unwrap().is_empty());
+    assert_eq!(texts(&db, not_exported_to("csv")).await.len(), 4);
+}
+
+#[tokio::test]
+async fn failed_recording_leaves_the_file_unpublished() {
+    let db = synced().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.csv");
+    let output = prepare_output(&Destination::File(path.clone()), false, false, |w| {
+        Ok(w.write_all(b"luggage")?)
+    })
+    .unwrap();
+    // a word can only be once in a batch
+    let mut words = all_words(&db).await;
+    words.push(words[0].clone());
+
+    let failed = db
+        .record_export_with(
+            "csv",
+            "csv",
+            &path.to_string_lossy(),
+            &ExportOptions::default(),
+            &words,
+            || output.publish(),
+        )
+        .await;
+    assert!(failed.is_err());
+    assert!(!path.exists());
+    assert!(db.get_exports(Some("csv")).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn large_batches_are_recorded() {
+    let db = synced().await;
+    let word = all_words(&db).await.remove(0);
+    let many: Vec<_> = (0..2000)
+        .map(|i| words::Model {
+            id: 10_000 + i,
+            ..word.clone()
+        })
+        .collect();
+    let batch = db.record_export("bulk", "csv", "-", &many).await.unwrap();
+    assert_eq!(batch.word_count, 2000);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+use skyeng_words::db::Store;
+use skyeng_words::sync;
+use wiremock::MockServer;
+
+#[tokio::test]
+async fn wordsets_and_words_can_be_inspected() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel"), (2, "Food")], (3, "My words")).await;
+    mount_wordset_words(&server, 1, &[1, 2]).await;
+    mount_wordset_words(&server, 2, &[2, 3]).await;
+    mount_wordset_words(&server, 3, &[]).await;
+    mount_meanings(
+        &server,
+        vec![
+            meaning(1, "luggage", "багаж", &["Pack your luggage."]),
+            meaning(2, "ticket", "билет", &[]),
+            meaning(3, "soup", "суп", &[]),
+        ],
+    )
+    .await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    let luggage = db.get_words_by_ids(&[1]).await.unwrap()[&1].clone();
+    let batch = db
+        .record_export("csv", "csv", "-", &[luggage])
+        .await
+        .unwrap();
+
+    let summaries = db.get_wordset_summaries(Some("csv"), false).await.unwrap();
+    let counts: Vec<(&str, usize, Option<usize>)> = summaries
+        .iter()
+        .map(|s| (s.wordset.name.as_str(), s.words, s.unexported))
+        .collect();
+    assert_eq!(
+        counts,
+        [
+            ("Travel", 2, Some(1)),
+            ("Food", 2, Some(2)),
+            ("My words", 0, Some(0))
+        ]
+    );
+    assert!(summaries[0].wordset.synced_at.is_some());
+
+    let word = db.get_word(1).await.unwrap();
+    assert_eq!(word.word.text, "luggage");
+    assert_eq!(word.examples, ["Pack your luggage."]);
+    assert_eq!(word.wordsets[0].name, "Travel");
+    let exports = db.get_word_exports(1).await.unwrap();
+    assert_eq!(exports.len(), 1);
+    assert_eq!((exports[0].0.id, exports[0].1), (batch.id, 1));
+    assert!(db.get_word_exports(2).await.unwrap().is_empty());
+    assert!(db.get_word(42).await.is_err());
+}
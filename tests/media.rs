@@ -0,0 +1,109 @@
+mod common;
+
+use common::*;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use skyeng_words::db::Store;
+use skyeng_words::export::{Anki, ExportOptions, Exporter};
+use skyeng_words::media::MediaStore;
+use skyeng_words::sync;
+use std::io::{Cursor, Read};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SOUND: &[u8] = b"ID3 fake mp3 payload";
+const IMAGE: &[u8] = b"\xff\xd8\xff fake jpeg payload";
+
+/// A meaning whose media are served by the mock server, both words share one picture
+fn meaning_with_media(server: &MockServer, id: i32, text: &str) -> Value {
+    let mut meaning = meaning(id, text, "перевод", &[]);
+    meaning["soundUrl"] = json!(format!("{}/sounds/{id}.mp3", server.uri()));
+    meaning["images"] = json!([{ "url": format!("{}/images/shared", server.uri()) }]);
+    meaning
+}
+
+async fn mount_asset(server: &MockServer, route: &str, body: &[u8], content_type: &str) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body.to_vec(), content_type))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn media_are_downloaded_once_and_bundled() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Store::in_memory().await.unwrap();
+
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel")], (2, "My words")).await;
+    mount_wordset_words(&server, 1, &[1, 2]).await;
+    mount_wordset_words(&server, 2, &[]).await;
+    mount_meanings(
+        &server,
+        vec![
+            meaning_with_media(&server, 1, "luggage"),
+            meaning_with_media(&server, 2, "ticket"),
+        ],
+    )
+    .await;
+    mount_asset(&server, "/sounds/1.mp3", SOUND, "audio/mpeg").await;
+    mount_asset(&server, "/sounds/2.mp3", b"another sound", "audio/mpeg").await;
+    mount_asset(&server, "/images/shared", IMAGE, "image/jpeg").await;
+
+    sync::sync(&client, &db, 4).await.unwrap();
+    let store = MediaStore::new(dir.path().join("media"));
+    let stats = sync::download_media(&client, &db, &store, 4).await.unwrap();
+    assert_eq!((stats.downloaded, stats.present, stats.failed), (3, 0, 0));
+
+    let urls = db.get_media_urls().await.unwrap();
+    assert_eq!(urls.len(), 3);
+    let media = db.get_media(&urls).await.unwrap();
+    let sound = &media[&format!("{}/sounds/1.mp3", server.uri())];
+    let checksum = format!("{:x}", Sha1::digest(SOUND));
+    assert_eq!(sound.checksum, checksum);
+    assert_eq!(sound.size, SOUND.len() as i64);
+    assert_eq!(sound.path, format!("{}/{checksum}.mp3", &checksum[..2]));
+    assert_eq!(std::fs::read(store.path_of(sound)).unwrap(), SOUND);
+    // no extension in the url, guessed from the content type
+    let image = &media[&format!("{}/images/shared", server.uri())];
+    assert!(image.path.ends_with(".jpg"));
+    assert_eq!(image.content_type.as_deref(), Some("image/jpeg"));
+
+    // files on disk are not fetched again
+    let requests = server.received_requests().await.unwrap().len();
+    let stats = sync::download_media(&client, &db, &store, 4).await.unwrap();
+    assert_eq!((stats.downloaded, stats.present, stats.failed), (0, 3, 0));
+    assert_eq!(server.received_requests().await.unwrap().len(), requests);
+
+    let entries = db.get_all_words(false).await.unwrap();
+    let options = ExportOptions {
+        media_dir: Some(store.dir().to_path_buf()),
+        ..Default::default()
+    };
+    let mut package = Vec::new();
+    Anki.export(&entries, &mut package, &options).unwrap();
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(package)).unwrap();
+    let mut names = String::new();
+    archive
+        .by_name("media")
+        .unwrap()
+        .read_to_string(&mut names)
+        .unwrap();
+    let names: std::collections::BTreeMap<String, String> = serde_json::from_str(&names).unwrap();
+    // the shared image is bundled once
+    assert_eq!(names.len(), 3);
+    let (index, _) = names
+        .iter()
+        .find(|(_, name)| name.as_str() == format!("{checksum}.mp3"))
+        .expect("sound is bundled");
+    let mut bundled = Vec::new();
+    archive
+        .by_name(index)
+        .unwrap()
+        .read_to_end(&mut bundled)
+        .unwrap();
+    assert_eq!(bundled, SOUND);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::*;
+use skyeng_words::db::Store;
+use skyeng_words::search::{self, SearchHit, SearchOptions};
+use skyeng_words::sync;
+use wiremock::MockServer;
+
+fn texts(hits: Vec<SearchHit>) -> Vec<String> {
+    hits.into_iter().map(|hit| hit.entry.word.text).collect()
+}
+
+async fn found(db: &Store, query: &str) -> Vec<String> {
+    texts(db.search(query, &SearchOptions::default()).await.unwrap())
+}
+
+async fn mount_food(server: &MockServer, meaning_ids: &[i32], bread_example: &str) {
+    mount_wordsets(server, &[(1, "Food")], (2, "My words")).await;
+    mount_wordset_words(server, 1, meaning_ids).await;
+    mount_wordset_words(server, 2, &[]).await;
+    mount_meanings(
+        server,
+        vec![
+            meaning(1, "bread", "хлеб", &[bread_example]),
+            meaning(2, "soup", "суп", &["Hot soup."]),
+            meaning(3, "ticket", "билет", &[]),
+        ],
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn search_ranks_and_highlights_words() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_food(&server, &[1, 2, 3], "Bread and soup for lunch.").await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    // a match in the text outweighs one in the examples
+    assert_eq!(found(&db, "soup").await, ["soup", "bread"]);
+    // terms are prefixes in any case
+    assert_eq!(found(&db, "BRE").await, ["bread"]);
+    assert_eq!(found(&db, "Хле").await, ["bread"]);
+    // English words are stemmed
+    assert_eq!(found(&db, "tickets").await, ["ticket"]);
+    // every term has to match
+    assert_eq!(found(&db, "soup, lunch").await, ["bread"]);
+    assert!(found(&db, "soup ticket").await.is_empty());
+    assert!(db.search("?!", &SearchOptions::default()).await.is_err());
+
+    let hits = db.search("lunch", &SearchOptions::default()).await.unwrap();
+    assert_eq!(hits[0].snippet, "Bread and soup for [lunch].");
+    let limited = SearchOptions {
+        limit: 1,
+        ..SearchOptions::default()
+    };
+    assert_eq!(texts(db.search("soup", &limited).await.unwrap()), ["soup"]);
+
+    // without the index words are matched the same way, only nothing is stemmed
+    let entries = db.get_all_words(false).await.unwrap();
+    let hits = search::search_entries(
+        entries.clone(),
+        &search::terms("SOUP"),
+        &SearchOptions::default(),
+    );
+    assert_eq!(
+        hits.iter()
+            .map(|hit| hit.snippet.as_str())
+            .collect::<Vec<_>>(),
+        ["[soup]", "Bread and [soup] for lunch."]
+    );
+    let hits = search::search_entries(entries, &search::terms("хл lun"), &SearchOptions::default());
+    assert_eq!(texts(hits), ["bread"]);
+
+    // the index follows changes: soup leaves the wordset, bread gets a new example
+    server.reset().await;
+    mount_login(&server).await;
+    mount_food(&server, &[1, 3], "Bread with butter.").await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    assert!(found(&db, "lunch").await.is_empty());
+    assert_eq!(found(&db, "butter").await, ["bread"]);
+    assert!(found(&db, "soup").await.is_empty());
+    let with_removed = SearchOptions {
+        include_removed: true,
+        ..SearchOptions::default()
+    };
+    assert_eq!(
+        texts(db.search("soup", &with_removed).await.unwrap()),
+        ["soup"]
+    );
+
+    db.prune_removed().await.unwrap();
+    assert!(db.search("soup", &with_removed).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn examples_are_indexed_in_order() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel")], (2, "My words")).await;
+    mount_wordset_words(&server, 1, &[1]).await;
+    mount_wordset_words(&server, 2, &[]).await;
+    let examples = [
+        "Pack it.",
+        "Lost it.",
+        "Found it.",
+        "Weigh it.",
+        "Check it.",
+    ];
+    mount_meanings(&server, vec![meaning(1, "luggage", "багаж", &examples)]).await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    let hits = db.search("check", &SearchOptions::default()).await.unwrap();
+    assert_eq!(
+        hits[0].snippet,
+        "Pack it.\nLost it.\nFound it.\nWeigh it.\n[Check] it."
+    );
+}
+
+#[tokio::test]
+async fn russian_words_are_not_stemmed() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel")], (2, "My words")).await;
+    mount_wordset_words(&server, 1, &[1]).await;
+    mount_wordset_words(&server, 2, &[]).await;
+    mount_meanings(&server, vec![meaning(1, "tickets", "билеты", &[])]).await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    assert_eq!(found(&db, "ticket").await, ["tickets"]);
+    assert_eq!(found(&db, "бил").await, ["tickets"]);
+    assert!(found(&db, "билетов").await.is_empty());
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::*;
+use sea_orm::{ConnectionTrait, DbBackend, Statement};
+use skyeng_words::db::Store;
+use skyeng_words::{stats, sync};
+use wiremock::MockServer;
+
+#[tokio::test]
+async fn stats_summarize_words() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel"), (2, "Food")], (3, "My words")).await;
+    mount_wordset_words(&server, 1, &[1, 2, 3]).await;
+    mount_wordset_words(&server, 2, &[4]).await;
+    mount_wordset_words(&server, 3, &[]).await;
+    let mut meanings = vec![
+        meaning(1, "luggage", "багаж", &[]),
+        meaning(2, "ticket", "билет", &[]),
+        meaning(3, "passport", "паспорт", &[]),
+        meaning(4, "soup", "суп", &[]),
+    ];
+    for (meaning, difficulty) in meanings.iter_mut().zip([1, 2, 2, 5]) {
+        meaning["difficultyLevel"] = difficulty.into();
+    }
+    mount_meanings(&server, meanings).await;
+    sync::sync(&client, &db, 4).await.unwrap();
+
+    // Monday 2022-06-20, then Wednesday and Sunday of the week of 2022-07-04
+    for (ids, added) in [("1", 1655719200), ("2, 3", 1657100000), ("4", 1657450000)] {
+        db.connection()
+            .execute(Statement::from_string(
+                DbBackend::Sqlite,
+                format!("UPDATE words SET created_at = {added} WHERE id IN ({ids})"),
+            ))
+            .await
+            .unwrap();
+    }
+    let exported = db.get_words_by_ids(&[1, 2]).await.unwrap();
+    let exported: Vec<_> = exported.into_values().collect();
+    db.record_export("csv", "csv", "-", &exported)
+        .await
+        .unwrap();
+
+    assert_eq!(stats::known_targets(&db).await.unwrap(), ["csv"]);
+    let targets = vec!["csv".to_string(), "xlsx".to_string()];
+    let stats = stats::collect(&db, &targets).await.unwrap();
+    assert_eq!(stats.words, 4);
+    let wordsets: Vec<(&str, usize)> = stats
+        .wordsets
+        .iter()
+        .map(|ws| (ws.name.as_str(), ws.words))
+        .collect();
+    assert_eq!(wordsets, [("Travel", 3), ("Food", 1), ("My words", 0)]);
+    let levels: Vec<(i32, usize)> = stats
+        .difficulty
+        .iter()
+        .map(|level| (level.level, level.words))
+        .collect();
+    assert_eq!(levels, [(1, 1), (2, 2), (5, 1)]);
+    // the fixture makes even ids Gold 3000
+    assert_eq!(stats.gold_3000.words, 2);
+    assert!((stats.gold_3000.coverage - 2.0 / 3000.0).abs() < 1e-9);
+    let weeks: Vec<(&str, usize, usize)> = stats
+        .weeks
+        .iter()
+        .map(|week| (week.start.as_str(), week.added, week.total))
+        .collect();
+    assert_eq!(
+        weeks,
+        [
+            ("2022-06-20", 1, 1),
+            ("2022-06-27", 0, 1),
+            ("2022-07-04", 3, 4)
+        ]
+    );
+    let progress: Vec<(&str, usize, usize)> = stats
+        .targets
+        .iter()
+        .map(|target| (target.target.as_str(), target.exported, target.pending))
+        .collect();
+    assert_eq!(progress, [("csv", 2, 2), ("xlsx", 0, 4)]);
+}
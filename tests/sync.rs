@@ -0,0 +1,369 @@
+mod common;
+
+use common::*;
+use serde_json::json;
+use skyeng_words::client::models::Meaning;
+use skyeng_words::client::{Client, Error};
+use skyeng_words::db::{Store, WordFilter};
+use skyeng_words::export::{Column, ExportOptions, Quoting};
+use skyeng_words::sync::{self, IdOrName};
+use std::time::Duration;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Syncs luggage and ticket of Travel, ticket and soup of Food
+async fn synced() -> (Store, MockServer, Client) {
+    let wordsets = [
+        (1, "Travel", &[1, 2][..]),
+        (2, "Food", &[2, 3]),
+        (3, "My words", &[]),
+    ];
+    let meanings = vec![
+        meaning(
+            1,
+            "luggage",
+            "багаж",
+            &["Pack your luggage.", "Lost luggage."],
+        ),
+        meaning(2, "ticket", "билет", &[]),
+        meaning(3, "soup", "суп", &["Hot soup."]),
+    ];
+    sync_account(&wordsets, meanings).await
+}
+
+/// Syncs again after luggage got a new translation and soup left the food wordset
+async fn resync(db: &Store, server: &MockServer, client: &Client) {
+    server.reset().await;
+    mount_login(server).await;
+    let wordsets = [
+        (1, "Travel", &[1, 2][..]),
+        (2, "Food", &[2]),
+        (3, "My words", &[]),
+    ];
+    let meanings = vec![
+        meaning(1, "luggage", "багаж, чемоданы", &["Pack your luggage."]),
+        meaning(2, "ticket", "билет", &[]),
+    ];
+    mount_account(server, &wordsets, meanings).await;
+    sync::sync(client, db, 4).await.unwrap();
+}
+
+#[tokio::test]
+async fn sync_stores_words_with_their_details() {
+    let (db, _server, _client) = synced().await;
+
+    let entries = db.get_all_words(false).await.unwrap();
+    let texts: Vec<&str> = entries.iter().map(|e| e.word.text.as_str()).collect();
+    assert_eq!(texts, vec!["luggage", "ticket", "soup"]);
+    assert_eq!(
+        entries[0].examples,
+        vec!["Pack your luggage.", "Lost luggage."]
+    );
+    assert_eq!(entries[0].word.definition, "definition of luggage");
+    assert_eq!(
+        entries[0].word.transcription.as_deref(),
+        Some("luggage-ipa")
+    );
+    assert_eq!(entries[0].part_of_speech(), Some("noun"));
+    assert_eq!(
+        entries[0].word.sound_url.as_deref(),
+        Some("https://cdn.example/sounds/1.mp3")
+    );
+    assert_eq!(
+        entries[0].alternatives().collect::<Vec<String>>(),
+        vec!["luggages — багаж (мн.)"]
+    );
+    assert!(entries[1].word.is_gold_3000);
+}
+
+#[tokio::test]
+async fn words_keep_every_wordset_they_belong_to() {
+    let (db, _server, _client) = synced().await;
+
+    let ticket = db.get_word(2).await.unwrap();
+    let ticket_wordsets: Vec<&str> = ticket.wordsets.iter().map(|ws| ws.name.as_str()).collect();
+    assert_eq!(ticket_wordsets, vec!["Travel", "Food"]);
+    assert_eq!(db.get_wordset_words(2, false).await.unwrap().len(), 2);
+    assert_eq!(db.get_ws_id_by_name("My words".into()).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn changed_words_get_a_new_revision() {
+    let (db, server, client) = synced().await;
+    let words: Vec<_> = db
+        .get_all_words(false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.word)
+        .collect();
+    db.record_export("quizlet", "csv", "words.csv", &words)
+        .await
+        .unwrap();
+
+    resync(&db, &server, &client).await;
+
+    let unexported = db.get_unexported_words("quizlet", false).await.unwrap();
+    assert_eq!(unexported.len(), 1);
+    let luggage = &unexported[0];
+    assert_eq!(luggage.word.translation, "багаж, чемоданы");
+    assert_eq!(luggage.word.revision, 2);
+    assert_eq!(luggage.examples, vec!["Pack your luggage."]);
+    assert_eq!(db.get_word(2).await.unwrap().word.revision, 1);
+}
+
+#[tokio::test]
+async fn words_gone_upstream_are_removed_then_pruned() {
+    let (db, server, client) = synced().await;
+    resync(&db, &server, &client).await;
+
+    let words = db.get_words_by_ids(&[2, 3]).await.unwrap();
+    assert!(words[&2].removed_at.is_none());
+    assert!(words[&3].removed_at.is_some());
+    assert_eq!(db.get_all_words(false).await.unwrap().len(), 2);
+    assert_eq!(db.get_all_words(true).await.unwrap().len(), 3);
+
+    let pruned = db.prune_removed().await.unwrap();
+    assert_eq!(pruned.words, 1);
+    assert_eq!(db.get_all_words(true).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn exports_are_kept_as_batches() {
+    let (db, _server, _client) = synced().await;
+    let words: Vec<_> = db
+        .get_all_words(false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.word)
+        .rev()
+        .collect();
+    let batch = db
+        .record_export("quizlet", "csv", "words.csv", &words)
+        .await
+        .unwrap();
+    assert_eq!(batch.word_count, 3);
+
+    let history = db.get_exports(None).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(
+        (history[0].format.as_str(), history[0].destination.as_str()),
+        ("csv", "words.csv")
+    );
+    // the batch keeps its order
+    let reexported = db.get_export(batch.id).await.unwrap();
+    let texts: Vec<&str> = reexported
+        .entries
+        .iter()
+        .map(|e| e.word.text.as_str())
+        .collect();
+    assert_eq!(texts, vec!["soup", "ticket", "luggage"]);
+    assert_eq!(reexported.options, Some(ExportOptions::default()));
+    assert!(db.get_export(batch.id + 1).await.is_err());
+}
+
+#[tokio::test]
+async fn batches_keep_their_options() {
+    let (db, _server, _client) = synced().await;
+    let words = db.find_word_rows(&WordFilter::default()).await.unwrap();
+    let options = ExportOptions {
+        columns: Some(vec![Column::IsGold3000, Column::Text]),
+        delimiter: Some(b';'),
+        quoting: Quoting::Always,
+        header: false,
+        examples_limit: Some(1),
+        examples_separator: Some(" | ".to_string()),
+        media_dir: Some("media".into()),
+        ..ExportOptions::default()
+    };
+    let batch = db
+        .record_export_with("quizlet", "csv", "-", &options, &words, || Ok(()))
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_export(batch.id).await.unwrap().options,
+        Some(options)
+    );
+}
+
+#[tokio::test]
+async fn batches_with_changed_or_pruned_words_are_not_reproduced() {
+    let (db, server, client) = synced().await;
+    let words = db.find_word_rows(&WordFilter::default()).await.unwrap();
+    let batch = db
+        .record_export("quizlet", "csv", "words.csv", &words)
+        .await
+        .unwrap();
+    let tickets = db
+        .record_export("quizlet", "csv", "tickets.csv", &words[1..2])
+        .await
+        .unwrap();
+
+    // luggage changes, soup leaves and is pruned
+    resync(&db, &server, &client).await;
+    db.prune_removed().await.unwrap();
+
+    let err = db.get_export(batch.id).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "export {} can't be reproduced, 1 of its words have changed and 1 have been pruned since",
+            batch.id
+        )
+    );
+    // untouched batches still come back
+    assert_eq!(db.get_export(tickets.id).await.unwrap().entries.len(), 1);
+}
+
+#[tokio::test]
+async fn wordset_synced_by_id_is_stored_with_its_words() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    let wordsets = [
+        (1, "Travel", &[1, 2][..]),
+        (2, "Food", &[3]),
+        (3, "My words", &[]),
+    ];
+    let meanings = vec![
+        meaning(1, "luggage", "багаж", &[]),
+        meaning(2, "ticket", "билет", &[]),
+        meaning(3, "soup", "суп", &[]),
+    ];
+    mount_account(&server, &wordsets, meanings).await;
+
+    sync::sync_wordset(&client, &db, IdOrName::Id(1), 4)
+        .await
+        .unwrap();
+    assert_eq!(db.get_ws_id_by_name("Travel".into()).await.unwrap(), 1);
+    assert_eq!(db.get_wordset_words(1, false).await.unwrap().len(), 2);
+    assert!(db.get_ws_id_by_name("Food".into()).await.is_err());
+    assert!(sync::sync_wordset(&client, &db, IdOrName::Id(4), 4)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn wordsets_gone_upstream_are_marked_removed() {
+    let (db, _server, _client) = synced().await;
+    assert_eq!(db.mark_removed_wordsets(&[1, 3]).await.unwrap(), 1);
+    // already removed ones don't count again
+    assert_eq!(db.mark_removed_wordsets(&[1, 3]).await.unwrap(), 0);
+    assert_eq!(db.get_all_words(false).await.unwrap().len(), 3);
+    db.refresh_removed_words().await.unwrap();
+    let texts: Vec<String> = db
+        .get_all_words(false)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.word.text)
+        .collect();
+    assert_eq!(texts, ["luggage", "ticket"]);
+}
+
+#[tokio::test]
+async fn concurrency_bounds_requests_across_wordsets() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    // two chunks of meanings in each of the two wordsets
+    let travel: Vec<i32> = (1..=60).collect();
+    let food: Vec<i32> = (61..=120).collect();
+    mount_wordsets(&server, &[(1, "Travel"), (2, "Food")], (3, "My words")).await;
+    mount_wordset_words(&server, 1, &travel).await;
+    mount_wordset_words(&server, 2, &food).await;
+    mount_wordset_words(&server, 3, &[]).await;
+    let delay = Duration::from_millis(200);
+    let meanings = (1..=120)
+        .map(|id| meaning(id, &format!("word {id}"), "слово", &[]))
+        .collect();
+    mount_slow_meanings(&server, meanings, delay).await;
+
+    let started = std::time::Instant::now();
+    sync::sync(&client, &db, 2).await.unwrap();
+    // four slow requests, no more than two at a time
+    assert!(started.elapsed() >= delay * 2, "{:?}", started.elapsed());
+    assert_eq!(db.get_all_words(false).await.unwrap().len(), 120);
+}
+
+#[tokio::test]
+async fn stores_do_not_share_data() {
+    let first = Store::in_memory().await.unwrap();
+    let second = Store::in_memory().await.unwrap();
+    first.record_export("csv", "csv", "-", &[]).await.unwrap();
+    assert_eq!(first.get_exports(None).await.unwrap().len(), 1);
+    assert!(second.get_exports(None).await.unwrap().is_empty());
+}
+
+fn meanings(ids: impl Iterator<Item = i32>) -> Vec<Meaning> {
+    ids.map(|id| {
+        serde_json::from_value(meaning(
+            id,
+            &format!("word {id}"),
+            "слово",
+            &["An example."],
+        ))
+        .unwrap()
+    })
+    .collect()
+}
+
+#[tokio::test]
+async fn large_batches_of_new_words_are_saved() {
+    let db = Store::in_memory().await.unwrap();
+    // far more bound variables than a single statement may take
+    db.save_new_words(meanings(1..=5000)).await.unwrap();
+
+    let words = db.get_all_words(false).await.unwrap();
+    assert_eq!(words.len(), 5000);
+    assert!(words
+        .iter()
+        .all(|w| w.examples.len() == 1 && w.alternatives.len() == 1));
+}
+
+#[tokio::test]
+async fn new_words_are_saved_all_or_nothing() {
+    let db = Store::in_memory().await.unwrap();
+    db.save_new_words(meanings(150..=150)).await.unwrap();
+
+    // the conflicting word comes after the first chunk went in
+    let err = db.save_new_words(meanings(1..=150)).await;
+    assert!(err.is_err());
+    let words = db.get_all_words(false).await.unwrap();
+    assert_eq!(words.len(), 1);
+    assert_eq!(words[0].examples.len(), 1);
+}
+
+#[tokio::test]
+async fn empty_page_before_the_total_fails_the_sync() {
+    let db = Store::in_memory().await.unwrap();
+    let server = MockServer::start().await;
+    let client = logged_in_client(&server).await;
+    mount_wordsets(&server, &[(1, "Travel")], (2, "My words")).await;
+    mount_wordset_words(&server, 2, &[]).await;
+    mount_meanings(&server, vec![meaning(1, "luggage", "багаж", &[])]).await;
+    // three words promised, the second page has none of the remaining two
+    for (number, data) in [("1", vec![json!({ "meaningId": 1 })]), ("2", vec![])] {
+        Mock::given(method("GET"))
+            .and(path("/api/v1/wordsets/1/words.json"))
+            .and(query_param("page", number))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(
+                data,
+                3,
+                number.parse().unwrap(),
+                100,
+            )))
+            .mount(&server)
+            .await;
+    }
+
+    let err = tokio::time::timeout(Duration::from_secs(5), sync::sync(&client, &db, 4))
+        .await
+        .expect("paging stops")
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InvalidSkyengData(_))
+    ));
+}